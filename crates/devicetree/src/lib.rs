@@ -120,6 +120,19 @@ impl<'tree> DeviceTree<'tree> {
         }
     }
 
+    /// Find all nodes that are compatible with any of the given names.
+    pub fn find_compatible<'names: 'tree>(
+        &'tree self,
+        names: &'names [&'names str],
+    ) -> impl Iterator<Item = Node<'tree>> {
+        self.nodes().filter(move |node| node.is_compatible(names))
+    }
+
+    /// Try to find the node that is referenced by the given phandle.
+    pub fn find_phandle(&'tree self, phandle: PHandle) -> Option<Node<'tree>> {
+        self.nodes().find(|node| node.phandle() == Some(phandle))
+    }
+
     /// Returns an iterator over the raw tokens of the structure block.
     pub fn tokens(&'tree self) -> TokenIter<'tree> {
         let start = self.struct_offset() as usize;
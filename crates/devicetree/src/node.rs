@@ -1,6 +1,6 @@
 use crate::{
     parse::{Token, TokenIter},
-    DeviceTree,
+    DeviceTree, PHandle,
 };
 use core::{convert::TryInto, iter::Fuse, num::NonZeroU32};
 
@@ -51,6 +51,35 @@ impl<'tree> Node<'tree> {
         .fuse()
     }
 
+    /// Check if any entry of this node's `compatible` property is inside `names`.
+    pub fn is_compatible(&self, names: &[&str]) -> bool {
+        self.prop("compatible").map_or(false, |prop| {
+            prop.as_strings().any(|name| names.contains(&name))
+        })
+    }
+
+    /// Returns the phandle of this node, if other nodes are able to reference it.
+    pub fn phandle(&self) -> Option<PHandle> {
+        self.prop("phandle")
+            .or_else(|| self.prop("linux,phandle"))
+            .and_then(|prop| prop.as_phandle())
+    }
+
+    /// Returns the phandle of the interrupt controller, this node's interrupts are routed to.
+    ///
+    /// Only the `interrupt-parent` property of this node is checked,
+    /// the value is not inherited from parent nodes.
+    pub fn interrupt_parent(&self) -> Option<PHandle> {
+        self.prop("interrupt-parent")?.as_phandle()
+    }
+
+    /// Returns an iterator over all cells of this node's `interrupts` property.
+    pub fn interrupts(&self) -> Cells<'tree> {
+        self.prop("interrupts")
+            .map(|prop| prop.as_cells())
+            .unwrap_or(Cells { data: &[] })
+    }
+
     /// Returns an iterator over all regions that are specified in this nodes `reg` property.
     pub fn regions(&self) -> Regions<'tree> {
         let address_cells = self.tree.root().prop("#address-cells");
@@ -109,9 +138,31 @@ impl<'tree> Property<'tree> {
     }
 
     /// Try to interpret the data of this property as a `PHandle`.
-    pub fn as_phandle(&self) -> Option<crate::PHandle> {
+    pub fn as_phandle(&self) -> Option<PHandle> {
         self.as_u32().map(Into::into)
     }
+
+    /// Returns an iterator that will interpret the data of this property
+    /// as a list of big endian `u32` cells.
+    pub fn as_cells(&self) -> Cells<'tree> {
+        Cells { data: self.data }
+    }
+}
+
+/// An iterator over the big endian `u32` cells of a property.
+#[derive(Clone)]
+pub struct Cells<'tree> {
+    data: &'tree [u8],
+}
+
+impl Iterator for Cells<'_> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let cell = self.data.get(..4)?;
+        self.data = &self.data[4..];
+        Some(u32::from_be_bytes(cell.try_into().ok()?))
+    }
 }
 
 /// An iterator over all children nodes of a single node.
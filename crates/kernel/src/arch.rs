@@ -2,21 +2,169 @@
 
 #[cfg(feature = "virt")]
 mod virt;
+
+use crate::{
+    drivers::syscon,
+    firmware::{self, Extension},
+};
+use core::{
+    marker::PhantomData,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+use devicetree::DeviceTree;
+use riscv::{csr::sstatus, sync::Mutex};
+use sbi::{
+    hsm::SuspendType,
+    system::{Reason, Type},
+};
+
+/// The devices that are used to power off and reboot the system, if the devicetree has them.
+static POWEROFF: Mutex<Option<syscon::Device>> = Mutex::new(None);
+static REBOOT: Mutex<Option<syscon::Device>> = Mutex::new(None);
+
+/// Whether the SBI implementation supports suspending harts.
+///
+/// This is assumed if the HSM extension exists, until the first suspend failed.
+static SUSPEND_SUPPORTED: AtomicBool = AtomicBool::new(true);
+
+/// Find the devices that are used to power off and reboot the system.
+///
+/// The registers of these devices must be mapped before [`exit`] or [`reboot`]
+/// is called, their addresses are returned by [`power_registers`].
+pub fn init(tree: &DeviceTree<'_>) {
+    let find = |names| {
+        tree.find_compatible(names)
+            .find_map(|node| syscon::Device::from_node(tree, &node))
+    };
+
+    let poweroff = find(syscon::POWEROFF_COMPATIBLE);
+    let reboot = find(syscon::REBOOT_COMPATIBLE);
+
+    without_interrupts(|| {
+        *POWEROFF.lock() = poweroff;
+        *REBOOT.lock() = reboot;
+    });
+}
+
+/// Return the physical addresses of all registers that are used to power off and reboot the system.
+pub fn power_registers() -> impl Iterator<Item = usize> {
+    let poweroff = without_interrupts(|| *POWEROFF.lock());
+    let reboot = without_interrupts(|| *REBOOT.lock());
+
+    poweroff.into_iter().chain(reboot).map(|dev| dev.addr())
+}
+
+/// Shut down the whole system, and report the given exit code if the platform supports it.
+///
+/// The `syscon-poweroff` device of the devicetree is preferred, and the
+/// SBI system reset extension is used if there's no such device.
+///
+/// The system reset is tried even if the extension wasn't probed, so this
+/// also works for panics that happen before the firmware is initialized.
+pub fn exit(code: u16) -> ! {
+    if let Some(dev) = without_interrupts(|| *POWEROFF.lock()) {
+        dev.write(poweroff_value(&dev, code));
+    }
+
+    // this fails harmlessly if the firmware doesn't implement the extension
+    let reason = match code {
+        0 => Reason::NoReason,
+        _ => Reason::SystemFailure,
+    };
+    let _ = sbi::system::reset(Type::Shutdown, reason);
+
+    wait_forever()
+}
+
+/// Reboot the whole system.
+///
+/// The `syscon-reboot` device of the devicetree is preferred, and the
+/// SBI system reset extension is used if there's no such device.
+pub fn reboot() -> ! {
+    if let Some(dev) = without_interrupts(|| *REBOOT.lock()) {
+        dev.trigger();
+    }
+
+    let _ = sbi::system::reset(Type::ColdReboot, Reason::NoReason);
+
+    wait_forever()
+}
+
 #[cfg(feature = "virt")]
-pub use virt::*;
+use virt::poweroff_value;
+
+/// Return the value that is written into the power off register to exit with the given code.
+#[cfg(not(feature = "virt"))]
+fn poweroff_value(dev: &syscon::Device, _code: u16) -> u32 {
+    dev.value()
+}
+
+/// Put this hart into a low power state until an interrupt is pending.
+///
+/// The hart is suspended through the SBI HSM extension if it's available, which
+/// allows the firmware to enter deeper sleep states than a plain `wfi`.
+pub fn wait_for_interrupt() {
+    if SUSPEND_SUPPORTED.load(Ordering::Relaxed) && firmware::has(Extension::Hsm) {
+        match sbi::hsm::suspend(SuspendType::DefaultRetentive, 0, 0) {
+            Ok(()) => return,
+            Err(_) => SUSPEND_SUPPORTED.store(false, Ordering::Relaxed),
+        }
+    }
 
-use core::time::Duration;
+    riscv::asm::wfi();
+}
 
-// Loop forever
-#[inline]
+/// Idle forever, and only wake up to handle interrupts.
 pub fn wait_forever() -> ! {
     loop {
-        riscv::asm::wfi();
+        wait_for_interrupt();
+    }
+}
+
+/// Run the given closure with supervisor interrupts disabled on this hart.
+///
+/// The previous interrupt state is restored after the closure returned.
+/// This must be used for locks that are also taken inside interrupt handlers,
+/// to prevent deadlocking the hart.
+pub fn without_interrupts<R>(f: impl FnOnce() -> R) -> R {
+    let _guard = disable_interrupts();
+    f()
+}
+
+/// Disable supervisor interrupts on this hart, until the returned guard is dropped.
+///
+/// This is the same as [`without_interrupts`], for places where the
+/// interrupts must stay disabled longer than a closure, like lock guards.
+pub fn disable_interrupts() -> InterruptGuard {
+    let enabled = sstatus::read().sie();
+    if enabled {
+        sstatus::clear_sie();
+    }
+
+    InterruptGuard {
+        enabled,
+        _not_send: PhantomData,
+    }
+}
+
+/// Guard that restores the previous interrupt state of this hart when dropped.
+///
+/// The guard can't be sent to another hart, because it restores the state of the current one.
+pub struct InterruptGuard {
+    enabled: bool,
+    _not_send: PhantomData<*mut ()>,
+}
+
+impl Drop for InterruptGuard {
+    fn drop(&mut self) {
+        if self.enabled {
+            sstatus::set_sie();
+        }
     }
 }
 
 /// Return the uptime of this hart.
 pub fn time() -> Duration {
-    let time = riscv::asm::rdtime();
-    Duration::from_nanos(time as u64 * 100)
+    crate::time::Instant::now().since_boot()
 }
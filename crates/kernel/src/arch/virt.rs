@@ -1,17 +1,21 @@
 //! Architecture specific functions for Qemu and other emulators
 
-const VIRT_TEST: *mut u32 = 0x10_0000 as *mut u32;
+use crate::drivers::syscon;
 
-/// Shuts down the whole CPU (Emulator).
-pub fn exit(code: u16) -> ! {
-    let status = match code as u32 {
-        0 => 0x5555,
-        code => (code << 16) | 0x3333u32,
-    };
+/// The value that QEMU's test device expects for a successful exit.
+const TEST_PASS: u32 = 0x5555;
+/// The value that QEMU's test device expects for a failing exit,
+/// with the exit code in the upper 16 bits.
+const TEST_FAIL: u32 = 0x3333;
 
-    unsafe {
-        core::ptr::write_volatile(VIRT_TEST, status);
+/// Return the value that is written into the power off register to exit with the given code.
+///
+/// The `syscon-poweroff` node of QEMU points to its test device, which
+/// can also report a failure exit code to the host.
+pub(super) fn poweroff_value(dev: &syscon::Device, code: u16) -> u32 {
+    match code {
+        0 => dev.value(),
+        code if dev.value() == TEST_PASS => ((code as u32) << 16) | TEST_FAIL,
+        _ => dev.value(),
     }
-
-    unreachable!()
 }
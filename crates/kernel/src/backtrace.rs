@@ -0,0 +1,70 @@
+//! Stack backtraces using the frame-pointer chain.
+//!
+//! The kernel is compiled with `-Cforce-frame-pointers=yes`, so every
+//! function stores the return address at `fp - 8`, and the frame pointer
+//! of the caller at `fp - 16`.
+
+use crate::{hart, ksyms};
+use core::fmt::Write;
+
+/// The maximum number of frames that will be visited, in case
+/// the frame-pointer chain is corrupted and contains a loop.
+const MAX_DEPTH: usize = 64;
+
+/// Read the frame pointer of the function that calls this function.
+#[inline(always)]
+pub fn frame_pointer() -> usize {
+    let fp: usize;
+    unsafe { asm!("mv {}, s0", out(reg) fp) };
+    fp
+}
+
+/// Walk the frame-pointer chain that starts at `fp` and call `f` with every
+/// return address that was found.
+///
+/// The walk stops as soon as a frame pointer is outside of the stack that
+/// contains the first frame.
+pub fn trace_from(mut fp: usize, mut f: impl FnMut(usize)) {
+    let (start, end) = match hart::stack_range(fp) {
+        Some(range) => range,
+        None => return,
+    };
+
+    for _ in 0..MAX_DEPTH {
+        // make sure that we can read the frame record of this frame
+        if fp % 8 != 0 || fp < start + 16 || fp > end {
+            break;
+        }
+
+        let (ra, prev) = unsafe {
+            let record = fp as *const usize;
+            (*record.sub(1), *record.sub(2))
+        };
+
+        if ra == 0 {
+            break;
+        }
+        f(ra);
+
+        // the stack grows downwards, so the frame of the caller must be above this frame
+        if prev <= fp {
+            break;
+        }
+        fp = prev;
+    }
+}
+
+/// Print a backtrace, starting at the given frame pointer, using the given console guard.
+pub fn print<G: Write>(guard: &mut G, fp: usize) {
+    error!(guard = guard; "Backtrace:");
+
+    let mut idx = 0;
+    trace_from(fp, |addr| {
+        error!(guard = guard; "  #{:<2} {:#018x} {}", idx, addr, ksyms::resolve(addr));
+        idx += 1;
+    });
+
+    if idx == 0 {
+        error!(guard = guard; "  no frames available.");
+    }
+}
@@ -1,64 +1,148 @@
 use crate::{
-    console, hart,
-    page::{sv39::Table, PageSize, Perm},
-    pmem, StaticCell,
+    arch, console, drivers, firmware,
+    page::{self, table::Table, PageSize, Perm, PhysAddr, VirtAddr},
+    pmem, time, trap,
+    unit::GIB,
+    StaticCell,
 };
 use devicetree::DeviceTree;
 use pmem::alloc::PAGE_SIZE;
-use riscv::{csr::satp, symbols};
+use riscv::{
+    csr::{satp, sie, sscratch, sstatus},
+    symbols,
+};
 
 static ROOT_TABLE: StaticCell<Table> = StaticCell::new(Table::new());
 
+/// The `satp` value, without the root table, that enables Sv39 paging.
+///
+/// The boot table always uses Sv39, because every hart that supports
+/// a larger addressing mode must support Sv39 too.
+const SATP_SV39: usize = 8 << 60;
+
+/// The page table that is used by the boot code, until the root table is set up.
+static BOOT_TABLE: BootTable = BootTable::new();
+
+/// A page table that only consists of gigapages, and can be created at compile time.
+///
+/// It maps the lower half to itself, so the boot code keeps running after enabling paging,
+/// the physmap, and the first 4GiB of physical memory at [`page::KERNEL_OFFSET`].
+#[repr(C, align(4096))]
+struct BootTable([u64; 512]);
+
+impl BootTable {
+    const fn new() -> Self {
+        // valid, readable, writable, executable, accessed and dirty
+        const FLAGS: u64 = 0b1100_1111;
+
+        const fn entry(paddr: usize) -> u64 {
+            // the physical page number starts at bit 10
+            (paddr >> 2) as u64 | FLAGS
+        }
+
+        const fn vpn(vaddr: usize) -> usize {
+            (vaddr >> 30) & 0x1FF
+        }
+
+        let mut entries = [0; 512];
+
+        let mut idx = 0;
+        while idx < 256 {
+            entries[idx] = entry(idx * GIB);
+            idx += 1;
+        }
+
+        let mut idx = 0;
+        while idx < page::PHYSMAP_SIZE / GIB {
+            entries[vpn(page::PHYSMAP_BASE) + idx] = entry(idx * GIB);
+            idx += 1;
+        }
+
+        let mut idx = 0;
+        while idx < 4 {
+            entries[vpn(page::KERNEL_OFFSET) + idx] = entry(idx * GIB);
+            idx += 1;
+        }
+
+        Self(entries)
+    }
+}
+
 /// Function that is run before `kinit` which is meant to setup paging and stuff
 /// and then jumps into `kinit`.
+///
+/// This already runs in the upper half using the boot table, but `fdt` is still
+/// the physical address of the device tree.
 #[no_mangle]
-unsafe extern "C" fn _before_main(hart: usize, fdt: *const u8) -> ! {
+unsafe extern "C" fn _before_main(hart: usize, fdt: PhysAddr) -> ! {
+    // disable all interrupts until we are ready to handle them
+    sstatus::clear_sie();
+    sie::write(0);
+
+    // there's no hart local storage yet
+    sscratch::write(0);
+
+    // find out what the SBI implementation supports, before anything uses it
+    firmware::init();
+
     // parse the device tree that is later used to initialize certain devices
-    let tree = DeviceTree::from_ptr(fdt);
+    let tree = DeviceTree::from_ptr(fdt.to_virt().as_ptr());
     let tree = tree.expect("failed to initialize devicetree");
 
-    // try to initialize uart debugging
-    let uart_addr = console::init(&tree).map(|x| {
-        info!("{} Uart console", "Initialized".green());
-        x
-    });
+    // try to initialize uart debugging, otherwise keep using the SBI console
+    let uart_addr = console::init(&tree);
+    match uart_addr {
+        Some(_) => info!("{} Uart console", "Initialized".green()),
+        None => info!("{} SBI console", "Using".green()),
+    }
+    firmware::report();
+
+    // install the trap vector so we can see any exception from now on
+    trap::init();
+
+    // read the timebase frequency, so the log shows correct timestamps
+    time::init(&tree).expect("failed to initialize the clock");
+
+    // find the devices that power off and reboot the system
+    arch::init(&tree);
 
     // make the physical memory allocator ready for allocation
     let heap = pmem::init(&tree).expect("failed to initialize the physical memory allocator");
 
+    // use the largest addressing mode that is supported by all harts
+    let mode = page::detect_mode(&tree);
+    page::set_mode(mode);
+    info!("{} {:?} addressing mode", "Using".green(), mode);
+
     // set up mapping
     let table = &mut *ROOT_TABLE.get();
 
     // map the device tree
     let len = pmem::alloc::align_up(tree.total_size() as usize, PAGE_SIZE);
-    table
-        .identity_map(
-            fdt.into(),
-            (fdt as usize + len).into(),
-            Perm::READ,
-            PageSize::Kilopage,
-        )
+    map_physmap(table, fdt.into(), usize::from(fdt) + len, Perm::READ)
         .expect("failed to map device tree");
 
-    // identity map the regions of the page allocator
+    // map the regions of the page allocator into the physmap
     for range in heap.as_slice() {
         let start = range.start as usize;
         let end = range.end as usize + 1;
 
-        table
-            .fit_identity_map(start.into(), end.into(), Perm::READ | Perm::WRITE)
+        map_physmap(table, start, end, Perm::READ | Perm::WRITE)
             .expect("failed to map heap region");
     }
 
-    // identity map all sections
+    // map all sections into the upper half, where the kernel is linked
     let mut map_section = |(start, end): (*mut u8, *mut u8), perm: Perm| {
+        let phys = |addr: *mut u8| VirtAddr::from(addr).to_phys().unwrap();
+
         table
-            .fit_identity_map(start.into(), end.into(), perm)
+            .fit_map(phys(start), phys(end), start.into(), perm)
             .expect("failed to map kernel section");
     };
 
     map_section(symbols::text_range(), Perm::READ | Perm::EXEC);
     map_section(symbols::rodata_range(), Perm::READ);
+    map_section(symbols::ksyms_range(), Perm::READ);
     map_section(symbols::data_range(), Perm::READ | Perm::WRITE);
     map_section(symbols::tdata_range(), Perm::READ | Perm::WRITE);
     map_section(symbols::bss_range(), Perm::READ | Perm::WRITE);
@@ -66,61 +150,94 @@ unsafe extern "C" fn _before_main(hart: usize, fdt: *const u8) -> ! {
 
     // map uart mmio device
     if let Some(uart) = uart_addr {
+        let uart = PhysAddr::from(uart);
         table
             .map(
-                uart.into(),
-                uart.into(),
+                uart,
+                uart.to_virt(),
                 PageSize::Kilopage,
                 Perm::READ | Perm::WRITE,
             )
             .expect("failed to map uart driver");
     }
 
-    table
-        .map(
-            0x10_0000.into(),
-            0x10_0000.into(),
-            PageSize::Kilopage,
-            Perm::WRITE | Perm::READ,
-        )
-        .unwrap();
+    // map the registers of the interrupt controller
+    for plic in tree.find_compatible(drivers::plic::COMPATIBLE) {
+        for region in plic.regions() {
+            map_physmap(
+                table,
+                region.start(),
+                region.end(),
+                Perm::READ | Perm::WRITE,
+            )
+            .expect("failed to map interrupt controller");
+        }
+    }
+
+    // map the registers that are used to power off and reboot the system
+    for reg in arch::power_registers() {
+        let reg = PhysAddr::from(reg & !(PAGE_SIZE - 1));
+        table
+            .map(
+                reg,
+                reg.to_virt(),
+                PageSize::Kilopage,
+                Perm::READ | Perm::WRITE,
+            )
+            .expect("failed to map system controller");
+    }
+
+    enable_paging();
+
+    // jump to the kernel main function
+    crate::kinit(hart, &tree)
+}
+
+/// Function that is run by every secondary hart before `kinit_secondary`,
+/// to install the page table that was set up by the boot hart.
+#[no_mangle]
+unsafe extern "C" fn _secondary_before_main(hart: usize) -> ! {
+    sstatus::clear_sie();
+    sie::write(0);
+    sscratch::write(0);
 
-    // enable paging
+    trap::init();
+    enable_paging();
+
+    crate::kinit_secondary(hart)
+}
+
+/// Map the given physical region into the physmap, using the best fitting page size.
+fn map_physmap(table: &mut Table, start: usize, end: usize, perm: Perm) -> Result<(), page::Error> {
+    let start = PhysAddr::from(start);
+    table.fit_map(start, end.into(), start.to_virt(), perm)
+}
+
+/// Enable paging on the current hart using the shared root page table.
+unsafe fn enable_paging() {
+    let root = VirtAddr::from(ROOT_TABLE.get()).to_phys().unwrap();
     let satp = satp::Satp {
-        mode: satp::Mode::Sv39,
+        mode: page::mode().satp(),
         asid: 0,
-        root_table: &ROOT_TABLE as *const _ as u64,
+        root_table: usize::from(root) as u64,
     };
 
     satp::write(satp);
     riscv::asm::sfence(None, None);
-
-    // jump to the kernel main function
-    crate::kinit(hart, &tree)
 }
 
 /// The entrypoint for the whole kernel.
 ///
+/// This runs at the physical address the kernel was loaded at, so only
+/// position independent code may be used until the boot table is enabled.
+///
 /// `a0` = hart id
-/// `a1` = pointer to device tree
+/// `a1` = physical address of the device tree
 #[naked]
 #[no_mangle]
 #[link_section = ".text.init"]
 pub unsafe extern "C" fn _boot() -> ! {
     asm!(
-        // ---------------------------------
-        // Load the global pointer into
-        // the `gp` register
-        // ---------------------------------
-        ".option push",
-        ".option norelax",
-        "    la gp, __global_pointer$",
-        ".option pop",
-        // ---------------------------------
-        // Disable interrupts
-        // ---------------------------------
-        "csrw sie, zero",
-        "csrci sstatus, 2",
         // ---------------------------------
         // Set `bss` to zero
         // ---------------------------------
@@ -132,6 +249,29 @@ pub unsafe extern "C" fn _boot() -> ! {
         "    addi t0, t0, 8",
         "zero_bss_done:",
         // ---------------------------------
+        // Enable paging using the boot
+        // table, and jump to the upper half
+        // ---------------------------------
+        "    la t0, {table}",
+        "    srli t0, t0, 12",
+        "    li t1, {sv39}",
+        "    or t0, t0, t1",
+        "    csrw satp, t0",
+        "    sfence.vma",
+        "    li t1, {offset}",
+        "    la t0, 1f",
+        "    add t0, t0, t1",
+        "    jr t0",
+        "1:",
+        // ---------------------------------
+        // Load the global pointer into
+        // the `gp` register
+        // ---------------------------------
+        ".option push",
+        ".option norelax",
+        "    la gp, __global_pointer$",
+        ".option pop",
+        // ---------------------------------
         // Initialize stack
         // ---------------------------------
         "    la sp, __stack_end",
@@ -139,6 +279,43 @@ pub unsafe extern "C" fn _boot() -> ! {
         // Jump into rust code
         // ---------------------------------
         "j _before_main",
+        table = sym BOOT_TABLE,
+        sv39 = const SATP_SV39,
+        offset = const page::KERNEL_OFFSET,
+        options(noreturn)
+    )
+}
+
+/// The entrypoint for every secondary hart that is started using the HSM extension.
+///
+/// Like [`_boot`], this runs at the physical address of the kernel.
+///
+/// `a0` = hart id
+/// `a1` = top of the stack that was allocated for this hart
+#[naked]
+#[no_mangle]
+pub unsafe extern "C" fn _secondary_boot() -> ! {
+    asm!(
+        "    la t0, {table}",
+        "    srli t0, t0, 12",
+        "    li t1, {sv39}",
+        "    or t0, t0, t1",
+        "    csrw satp, t0",
+        "    sfence.vma",
+        "    li t1, {offset}",
+        "    la t0, 1f",
+        "    add t0, t0, t1",
+        "    jr t0",
+        "1:",
+        ".option push",
+        ".option norelax",
+        "    la gp, __global_pointer$",
+        ".option pop",
+        "    mv sp, a1",
+        "j _secondary_before_main",
+        table = sym BOOT_TABLE,
+        sv39 = const SATP_SV39,
+        offset = const page::KERNEL_OFFSET,
         options(noreturn)
     )
 }
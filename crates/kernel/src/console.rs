@@ -1,25 +1,59 @@
 //! Implementation for accessing stdout/stdin.
 //!
 //! This module also contains print macros.
+//!
+//! The SBI console is used until [`init`] found the `stdout` device, or if
+//! there's no supported `stdout` device. The console is polled until
+//! [`init_interrupts`] switches it into interrupt-driven mode.
 
-use crate::drivers;
-use core::fmt::{self, Write};
+use crate::{
+    arch::{self, InterruptGuard},
+    drivers, irq,
+    page::PhysAddr,
+};
+use core::{
+    fmt::{self, Write},
+    ops::{Deref, DerefMut},
+};
 use devicetree::{node::ChosenNode, DeviceTree};
 use riscv::sync::{Mutex, MutexGuard};
 
-pub static CONSOLE: Mutex<StaticConsoleDevice> = Mutex::new(StaticConsoleDevice(None));
+/// The global console, which must only be accessed through [`lock`].
+static CONSOLE: Mutex<StaticConsoleDevice> = Mutex::new(StaticConsoleDevice(Some(
+    ConsoleDevice::Sbi(drivers::sbi::Device::new()),
+)));
+
+/// The interrupt-driven console device, which is only used after [`init_interrupts`].
+static BUFFERED: drivers::ns16550a::Buffered = drivers::ns16550a::Buffered::new();
+
+displaydoc_lite::displaydoc! {
+    /// Errors that can happen while switching the console into interrupt-driven mode.
+    #[derive(Debug)]
+    pub enum Error {
+        /// the console device doesn't support interrupts
+        Unsupported,
+        /// the console device has no interrupt that is connected to the interrupt controller
+        NoInterrupt,
+        /// {_0}
+        Irq(irq::Error),
+    }
+}
 
 /// Console device that can be used inside a static context.
 pub struct StaticConsoleDevice(Option<ConsoleDevice>);
 
 impl StaticConsoleDevice {
-    /// Write the given byte into this device.
+    /// Write the given string into this device.
     ///
-    /// If it hasn't initialized yet, it will be a no-op.
+    /// If there's no device, it will be a no-op.
     pub fn write(&mut self, s: &str) -> fmt::Result {
-        if let Some(ref mut dev) = self.0 {
+        if BUFFERED.is_enabled() {
+            BUFFERED.write(s.as_bytes());
+            Ok(())
+        } else if let Some(ref mut dev) = self.0 {
             match dev {
                 ConsoleDevice::NS16550(dev) => dev.write_str(s),
+                ConsoleDevice::Sbi(dev) => dev.write_str(s),
             }
         } else {
             Ok(())
@@ -39,6 +73,8 @@ unsafe impl Sync for StaticConsoleDevice {}
 /// All different kinds of devices that can be used as a console.
 pub enum ConsoleDevice {
     NS16550(drivers::ns16550a::Device),
+    /// The console of the SBI implementation, used as the early-boot and fallback console.
+    Sbi(drivers::sbi::Device),
 }
 
 impl ConsoleDevice {
@@ -54,7 +90,8 @@ impl ConsoleDevice {
 
         if compatible.any(|name| drivers::ns16550a::COMPATIBLE.contains(&name)) {
             let addr = stdout.regions().next()?.start();
-            Some((Self::NS16550(drivers::ns16550a::Device::new(addr)), addr))
+            let regs = PhysAddr::from(addr).to_virt().into();
+            Some((Self::NS16550(drivers::ns16550a::Device::new(regs)), addr))
         } else {
             None
         }
@@ -63,6 +100,7 @@ impl ConsoleDevice {
     fn init(&mut self) {
         match self {
             ConsoleDevice::NS16550(dev) => dev.init(),
+            ConsoleDevice::Sbi(_) => {}
         }
     }
 }
@@ -70,20 +108,111 @@ impl ConsoleDevice {
 /// Initializes the global console by finding the right device that should
 /// be used according to the given `/chosen` node of the given tree.
 ///
-/// Returns `Some` with the physical address of the uart driver, if there's a uart device
+/// Returns `Some` with the physical address of the uart driver, if there's a uart device.
+/// Otherwise the SBI console keeps being used.
 pub fn init(tree: &DeviceTree<'_>) -> Option<usize> {
     if let Some((mut dev, addr)) = unsafe { ConsoleDevice::from_chosen(&tree.chosen()) } {
         dev.init();
-        CONSOLE.lock().0 = Some(dev);
+        lock().0 = Some(dev);
         Some(addr)
     } else {
         None
     }
 }
 
+/// Switch the console into interrupt-driven mode, by registering
+/// an interrupt handler for the `stdout` device.
+///
+/// The interrupt controller must be [initialized](irq::init) before.
+pub fn init_interrupts(tree: &DeviceTree<'_>) -> Result<(), Error> {
+    let stdout = tree.chosen().stdout().ok_or(Error::Unsupported)?;
+    if !stdout.is_compatible(drivers::ns16550a::COMPATIBLE) {
+        return Err(Error::Unsupported);
+    }
+
+    let addr = stdout.regions().next().ok_or(Error::Unsupported)?.start();
+    let addr = PhysAddr::from(addr).to_virt().into();
+    let irq = irq::sources(tree, &stdout)
+        .next()
+        .ok_or(Error::NoInterrupt)?;
+
+    // make sure nobody is printing while the device is switched
+    let _guard = lock();
+    unsafe { BUFFERED.enable(addr) };
+
+    irq::register_irq(irq, |_| BUFFERED.handle_interrupt()).map_err(|err| {
+        BUFFERED.disable();
+        Error::Irq(err)
+    })
+}
+
+/// Switch the console back to polling, after sending all buffered output.
+///
+/// This is used for output that must be visible, even if
+/// interrupts are never handled again, like panic messages.
+pub fn switch_to_polling() {
+    BUFFERED.disable();
+}
+
+/// Read the next byte from the console, without blocking.
+pub fn try_read() -> Option<u8> {
+    if BUFFERED.is_enabled() {
+        BUFFERED.try_read()
+    } else {
+        match lock().0 {
+            Some(ConsoleDevice::NS16550(ref mut dev)) => dev.try_read(),
+            Some(ConsoleDevice::Sbi(ref mut dev)) => dev.try_read(),
+            None => None,
+        }
+    }
+}
+
+/// Wait until a byte is available on the console, and return it.
+pub fn read() -> u8 {
+    if BUFFERED.is_enabled() {
+        return BUFFERED.read();
+    }
+
+    loop {
+        if let Some(x) = try_read() {
+            return x;
+        }
+    }
+}
+
 /// Lock the console and return a guard that can write to the console.
-pub fn lock() -> MutexGuard<'static, StaticConsoleDevice> {
-    CONSOLE.lock()
+///
+/// Interrupts are disabled on this hart while the guard is alive, because
+/// interrupt handlers may log, which would deadlock if they interrupted
+/// the hart while it is printing.
+pub fn lock() -> ConsoleGuard {
+    let irq = arch::disable_interrupts();
+    ConsoleGuard {
+        guard: CONSOLE.lock(),
+        _irq: irq,
+    }
+}
+
+/// Guard that gives exclusive access to the console, and
+/// keeps interrupts disabled until it's dropped.
+pub struct ConsoleGuard {
+    // the fields are dropped in order, so the lock is released before interrupts are enabled
+    guard: MutexGuard<'static, StaticConsoleDevice>,
+    _irq: InterruptGuard,
+}
+
+impl Deref for ConsoleGuard {
+    type Target = StaticConsoleDevice;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+
+impl DerefMut for ConsoleGuard {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.guard
+    }
 }
 
 #[macro_export]
@@ -99,5 +228,5 @@ macro_rules! println {
 
 #[doc(hidden)]
 pub fn _print(args: core::fmt::Arguments<'_>) {
-    let _ = CONSOLE.lock().write_fmt(args);
+    let _ = lock().write_fmt(args);
 }
@@ -1,3 +1,6 @@
 //! Different drivers for different kinds of devices.
 
 pub mod ns16550a;
+pub mod plic;
+pub mod sbi;
+pub mod syscon;
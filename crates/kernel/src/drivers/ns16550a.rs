@@ -1,7 +1,12 @@
 //! Driver for the `ns16550a` UART chip.
 
 use self::registers::*;
-use core::fmt;
+use crate::arch;
+use core::{
+    fmt,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use riscv::sync::Mutex;
 
 mod registers {
     use rumio::{define_mmio_register, define_mmio_struct, mmio::Lit};
@@ -11,11 +16,31 @@ mod registers {
         LSR: u8 {
             /// Indicate if there's data to read
             r DATA_READY: 0,
+            /// Check if the transmitter holding register is empty
+            r THR_EMPTY: 5,
             /// Check if the transmitter lane is empty
             r TRANSMITTER_EMPTY: 6,
         }
     }
 
+    define_mmio_register! {
+        /// The Interrupt-Enable-Register
+        IER: u8 {
+            /// Raise an interrupt if there's data to read
+            rw RX_AVAILABLE: 0,
+            /// Raise an interrupt if the transmitter holding register is empty
+            rw TX_EMPTY: 1,
+        }
+    }
+
+    define_mmio_register! {
+        /// The Modem-Control-Register
+        MCR: u8 {
+            /// Auxiliary output 2, which connects the interrupt line on most boards
+            rw OUT2: 3,
+        }
+    }
+
     define_mmio_register! {
         /// The FIFO-Control-Register
         FCR: u8 {
@@ -44,8 +69,10 @@ mod registers {
         /// The raw MMIO block for controlling the ns16550a chip.
         pub struct Registers {
             (0x00 => DATA: Lit<u8>),
+            (0x01 => IER: IER),
             (0x02 => FCR: FCR),
             (0x03 => LCR: LCR),
+            (0x04 => MCR: MCR),
             (0x05 => LSR: LSR),
         }
     }
@@ -54,6 +81,12 @@ mod registers {
 /// The list of names that this device is compatible with.
 pub const COMPATIBLE: &[&str] = &["ns16550a", "ns16550"];
 
+/// The size of the receive and transmit buffers of a [`Buffered`] device.
+pub const BUFFER_SIZE: usize = 1024;
+
+/// The number of bytes that fit into the transmitter FIFO.
+const FIFO_SIZE: usize = 16;
+
 /// Driver for the `ns16550a` chip.
 pub struct Device {
     regs: Registers,
@@ -79,9 +112,9 @@ impl Device {
         // set the word length to 8 bits
         self.regs.LCR().WORD_LEN().set(WordLen::Eight);
 
-        // TODO:
-        // enable data available interrupt
-        // self.regs.ier().modify(IER::DATA_READY::SET);
+        // interrupts stay disabled until a `Buffered` device takes over
+        self.set_rx_interrupt(false);
+        self.set_tx_interrupt(false);
 
         let divisor = 592;
         let low = (divisor & 0xFF) as u8;
@@ -146,6 +179,26 @@ impl Device {
         self.regs.DATA().write(x);
     }
 
+    /// Enable, or disable, the interrupt that is raised if there's data to read.
+    pub fn set_rx_interrupt(&mut self, enabled: bool) {
+        self.regs.IER().RX_AVAILABLE().set(enabled);
+    }
+
+    /// Enable, or disable, the interrupt that is raised if the transmitter
+    /// holding register is empty.
+    pub fn set_tx_interrupt(&mut self, enabled: bool) {
+        self.regs.IER().TX_EMPTY().set(enabled);
+    }
+
+    /// Connect, or disconnect, the interrupt line of this device.
+    pub fn set_interrupt_line(&mut self, enabled: bool) {
+        self.regs.MCR().OUT2().set(enabled);
+    }
+
+    fn thr_empty(&self) -> bool {
+        self.regs.LSR().read(THR_EMPTY::FIELD) != 0
+    }
+
     fn transmitter_empty(&self) -> bool {
         self.regs.LSR().read(TRANSMITTER_EMPTY::FIELD) != 0
     }
@@ -165,3 +218,208 @@ impl fmt::Write for Device {
         Ok(())
     }
 }
+
+/// Interrupt-driven access to a `ns16550a` device.
+///
+/// Incoming and outgoing data is buffered inside ring buffers, which
+/// are filled and drained by [`Buffered::handle_interrupt`].
+pub struct Buffered {
+    /// The base address of the device, or `0` if interrupt mode is disabled.
+    addr: AtomicUsize,
+    rx: Mutex<RingBuffer<BUFFER_SIZE>>,
+    tx: Mutex<RingBuffer<BUFFER_SIZE>>,
+}
+
+impl Buffered {
+    /// Create a new buffered device, that is disabled until [`Buffered::enable`] is called.
+    pub const fn new() -> Self {
+        Self {
+            addr: AtomicUsize::new(0),
+            rx: Mutex::new(RingBuffer::new()),
+            tx: Mutex::new(RingBuffer::new()),
+        }
+    }
+
+    /// Switch the device at the given address into interrupt-driven mode.
+    ///
+    /// # Safety
+    ///
+    /// The `addr` must be a valid, and initialized, `ns16550a` MMIO device.
+    pub unsafe fn enable(&self, addr: usize) {
+        let mut dev = Device::new(addr);
+        self.addr.store(addr, Ordering::Release);
+
+        dev.set_rx_interrupt(true);
+        dev.set_interrupt_line(true);
+    }
+
+    /// Disable interrupt-driven mode after sending all buffered output by polling.
+    ///
+    /// This is used to make sure that panic messages are printed,
+    /// even if interrupts will never be handled again.
+    pub fn disable(&self) {
+        let mut dev = match self.device() {
+            Some(dev) => dev,
+            None => return,
+        };
+
+        arch::without_interrupts(|| {
+            dev.set_rx_interrupt(false);
+            dev.set_tx_interrupt(false);
+
+            // the lock may be held by the code that failed, so don't wait for it
+            if let Some(mut tx) = self.tx.try_lock() {
+                while let Some(x) = tx.pop() {
+                    dev.write(x);
+                }
+            }
+
+            self.addr.store(0, Ordering::Release);
+        });
+    }
+
+    /// Check if this device is in interrupt-driven mode.
+    pub fn is_enabled(&self) -> bool {
+        self.addr.load(Ordering::Acquire) != 0
+    }
+
+    /// Take the next byte out of the receive buffer, without blocking.
+    pub fn try_read(&self) -> Option<u8> {
+        arch::without_interrupts(|| self.rx.lock().pop())
+    }
+
+    /// Wait until a byte was received and return it.
+    ///
+    /// The hart sleeps until the next interrupt arrives, while the buffer is empty.
+    pub fn read(&self) -> u8 {
+        loop {
+            // waiting with interrupts disabled makes sure that we can't miss
+            // the interrupt between checking the buffer and going to sleep
+            let x = arch::without_interrupts(|| {
+                let x = self.rx.lock().pop();
+                if x.is_none() {
+                    riscv::asm::wfi();
+                }
+                x
+            });
+
+            if let Some(x) = x {
+                return x;
+            }
+        }
+    }
+
+    /// Put the given bytes into the transmit buffer and start sending them.
+    ///
+    /// If the buffer is full, the oldest bytes are sent by polling the device.
+    pub fn write(&self, bytes: &[u8]) {
+        let mut dev = match self.device() {
+            Some(dev) => dev,
+            None => return,
+        };
+
+        arch::without_interrupts(|| {
+            let mut tx = self.tx.lock();
+
+            for &x in bytes {
+                if tx.is_full() {
+                    let oldest = tx.pop().unwrap();
+                    dev.write(oldest);
+                }
+                tx.push(x);
+            }
+
+            transmit(&mut dev, &mut tx);
+        });
+    }
+
+    /// Handle an interrupt of this device, by moving received data into the
+    /// receive buffer and sending data from the transmit buffer.
+    ///
+    /// Received data is dropped if the receive buffer is full.
+    pub fn handle_interrupt(&self) {
+        let mut dev = match self.device() {
+            Some(dev) => dev,
+            None => return,
+        };
+
+        let mut rx = self.rx.lock();
+        while let Some(x) = dev.try_read() {
+            rx.push(x);
+        }
+        drop(rx);
+
+        transmit(&mut dev, &mut self.tx.lock());
+    }
+
+    fn device(&self) -> Option<Device> {
+        match self.addr.load(Ordering::Acquire) {
+            0 => None,
+            addr => Some(unsafe { Device::new(addr) }),
+        }
+    }
+}
+
+/// Fill the transmitter FIFO with bytes from the buffer, and only keep
+/// the transmitter interrupt enabled if there are bytes left.
+fn transmit(dev: &mut Device, tx: &mut RingBuffer<BUFFER_SIZE>) {
+    if dev.thr_empty() {
+        for _ in 0..FIFO_SIZE {
+            match tx.pop() {
+                Some(x) => unsafe { dev.write_data(x) },
+                None => break,
+            }
+        }
+    }
+
+    dev.set_tx_interrupt(!tx.is_empty());
+}
+
+/// A fixed size FIFO queue of bytes.
+struct RingBuffer<const N: usize> {
+    buf: [u8; N],
+    /// The index of the oldest byte inside the buffer.
+    head: usize,
+    len: usize,
+}
+
+impl<const N: usize> RingBuffer<N> {
+    const fn new() -> Self {
+        Self {
+            buf: [0; N],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Append a byte to the buffer. Returns `false` if the buffer is full.
+    fn push(&mut self, x: u8) -> bool {
+        if self.is_full() {
+            return false;
+        }
+
+        self.buf[(self.head + self.len) % N] = x;
+        self.len += 1;
+        true
+    }
+
+    /// Remove the oldest byte from the buffer.
+    fn pop(&mut self) -> Option<u8> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let x = self.buf[self.head];
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        Some(x)
+    }
+}
@@ -0,0 +1,98 @@
+//! Driver for the RISC-V Platform-Level Interrupt Controller (PLIC).
+
+use core::ptr;
+
+/// The list of names that this device is compatible with.
+pub const COMPATIBLE: &[&str] = &["riscv,plic0", "sifive,plic-1.0.0"];
+
+/// The maximum number of interrupt sources a PLIC can have.
+///
+/// Source `0` is reserved and means "no interrupt".
+pub const MAX_SOURCES: usize = 1024;
+
+/// The interrupt number, inside the `interrupts-extended` property of the PLIC,
+/// that identifies a supervisor mode context.
+pub const SUPERVISOR_EXTERNAL: u32 = 9;
+
+/// Offset of the per-source priority registers.
+const PRIORITY_OFFSET: usize = 0x00_0000;
+/// Offset of the per-context enable bits.
+const ENABLE_OFFSET: usize = 0x00_2000;
+/// Number of bytes between the enable bits of two contexts.
+const ENABLE_STRIDE: usize = 0x80;
+/// Offset of the per-context threshold and claim registers.
+const CONTEXT_OFFSET: usize = 0x20_0000;
+/// Number of bytes between the threshold registers of two contexts.
+const CONTEXT_STRIDE: usize = 0x1000;
+
+/// Driver for a PLIC.
+#[derive(Clone, Copy)]
+pub struct Device {
+    base: usize,
+    sources: u32,
+}
+
+impl Device {
+    /// Create a new PLIC device at the given base address, that
+    /// handles `sources` interrupt sources.
+    ///
+    /// # Safety
+    ///
+    /// The `base` must be a valid PLIC MMIO device.
+    pub const unsafe fn new(base: usize, sources: u32) -> Self {
+        Self { base, sources }
+    }
+
+    /// Return the number of interrupt sources this PLIC has.
+    pub fn sources(&self) -> u32 {
+        self.sources
+    }
+
+    /// Set the priority of the given interrupt source.
+    ///
+    /// A priority of `0` means "never interrupt".
+    pub fn set_priority(&self, irq: u32, priority: u32) {
+        self.write(PRIORITY_OFFSET + irq as usize * 4, priority);
+    }
+
+    /// Set the priority threshold of the given context.
+    ///
+    /// Only interrupts with a priority that is greater than the
+    /// threshold will be delivered to the context.
+    pub fn set_threshold(&self, context: usize, threshold: u32) {
+        self.write(CONTEXT_OFFSET + context * CONTEXT_STRIDE, threshold);
+    }
+
+    /// Enable, or disable, the given interrupt source for the given context.
+    pub fn set_enabled(&self, context: usize, irq: u32, enabled: bool) {
+        let offset = ENABLE_OFFSET + context * ENABLE_STRIDE + (irq as usize / 32) * 4;
+        let bit = 1 << (irq % 32);
+
+        let bits = self.read(offset);
+        let bits = if enabled { bits | bit } else { bits & !bit };
+        self.write(offset, bits);
+    }
+
+    /// Claim the highest priority pending interrupt for the given context.
+    ///
+    /// Returns `None` if there's no pending interrupt.
+    pub fn claim(&self, context: usize) -> Option<u32> {
+        match self.read(CONTEXT_OFFSET + context * CONTEXT_STRIDE + 4) {
+            0 => None,
+            irq => Some(irq),
+        }
+    }
+
+    /// Signal the PLIC that the given, previously claimed, interrupt was handled.
+    pub fn complete(&self, context: usize, irq: u32) {
+        self.write(CONTEXT_OFFSET + context * CONTEXT_STRIDE + 4, irq);
+    }
+
+    fn read(&self, offset: usize) -> u32 {
+        unsafe { ptr::read_volatile((self.base + offset) as *const u32) }
+    }
+
+    fn write(&self, offset: usize, val: u32) {
+        unsafe { ptr::write_volatile((self.base + offset) as *mut u32, val) }
+    }
+}
@@ -0,0 +1,83 @@
+//! Console driver that uses the SBI implementation to print and read data.
+//!
+//! The Debug Console extension is preferred, but if it's not available
+//! the legacy `console_putchar` extension is used to write data.
+//! If neither of them exists, all data is dropped.
+
+use crate::{
+    firmware::{self, Extension},
+    page::VirtAddr,
+};
+use core::fmt;
+
+/// A console that is provided by the SBI implementation.
+pub struct Device {
+    /// Whether the Debug Console extension is usable.
+    ///
+    /// This is assumed if the extension exists, until a call to the extension fails.
+    dbcn: bool,
+}
+
+impl Device {
+    /// Create a new SBI console device.
+    pub const fn new() -> Self {
+        Self { dbcn: true }
+    }
+
+    fn dbcn(&self) -> bool {
+        self.dbcn && firmware::has(Extension::Dbcn)
+    }
+
+    /// Tries to read incoming data, but will return `None`
+    /// if there's no data available.
+    ///
+    /// Reading is only supported by the Debug Console extension.
+    pub fn try_read(&mut self) -> Option<u8> {
+        if !self.dbcn() {
+            return None;
+        }
+
+        // every stack is inside a linear mapping, so it always has a physical address
+        let mut x = 0u8;
+        let addr = VirtAddr::from(&mut x as *mut u8).to_phys()?;
+        match unsafe { sbi::dbcn::console_read(1, addr.into()) } {
+            Ok(1) => Some(x),
+            Ok(_) => None,
+            Err(_) => {
+                self.dbcn = false;
+                None
+            }
+        }
+    }
+
+    /// Write all the given bytes to the console.
+    pub fn write(&mut self, mut bytes: &[u8]) {
+        while self.dbcn() && !bytes.is_empty() {
+            // bytes outside of the linear mappings are written using the legacy extension
+            let addr = match VirtAddr::from(bytes.as_ptr()).to_phys() {
+                Some(addr) => addr,
+                None => break,
+            };
+
+            match unsafe { sbi::dbcn::console_write(bytes.len(), addr.into()) } {
+                Ok(written) => bytes = &bytes[written.min(bytes.len())..],
+                Err(_) => self.dbcn = false,
+            }
+        }
+
+        if !firmware::has(Extension::LegacyConsole) {
+            return;
+        }
+
+        for &x in bytes {
+            let _ = sbi::legacy::console_putchar(x);
+        }
+    }
+}
+
+impl fmt::Write for Device {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write(s.as_bytes());
+        Ok(())
+    }
+}
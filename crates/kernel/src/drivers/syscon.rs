@@ -0,0 +1,75 @@
+//! Driver for the `syscon-poweroff` and `syscon-reboot` devices, which power off,
+//! or reboot, the system by writing a value into a register of a system controller.
+
+use crate::page::PhysAddr;
+use core::ptr;
+use devicetree::{node::Node, DeviceTree};
+
+/// The list of names that the power off device is compatible with.
+pub const POWEROFF_COMPATIBLE: &[&str] = &["syscon-poweroff"];
+
+/// The list of names that the reboot device is compatible with.
+pub const REBOOT_COMPATIBLE: &[&str] = &["syscon-reboot"];
+
+/// A single register of a system controller, and the value that triggers the action.
+#[derive(Debug, Clone, Copy)]
+pub struct Device {
+    addr: usize,
+    value: u32,
+    mask: u32,
+}
+
+impl Device {
+    /// Create a device from a `syscon-poweroff` or `syscon-reboot` node.
+    ///
+    /// Returns `None` if the node, or the system controller it points to, is invalid.
+    pub fn from_node(tree: &DeviceTree<'_>, node: &Node<'_>) -> Option<Self> {
+        let prop = |name| node.prop(name).and_then(|prop| prop.as_u32());
+
+        let regmap = tree.find_phandle(prop("regmap")?.into())?;
+        let base = regmap.regions().next()?.start();
+        let offset = prop("offset")?;
+
+        // old trees only specify the mask, which is also the value then
+        let (value, mask) = match (prop("value"), prop("mask")) {
+            (Some(value), mask) => (value, mask.unwrap_or(u32::MAX)),
+            (None, Some(mask)) => (mask, u32::MAX),
+            (None, None) => return None,
+        };
+
+        Some(Self {
+            addr: base + offset as usize,
+            value,
+            mask,
+        })
+    }
+
+    /// Return the physical address of the register, which must be mapped before
+    /// this device is used.
+    pub fn addr(&self) -> usize {
+        self.addr
+    }
+
+    /// Return the value that is written into the register to trigger the action.
+    pub fn value(&self) -> u32 {
+        self.value
+    }
+
+    /// Trigger the action of this device.
+    pub fn trigger(&self) {
+        self.write(self.value);
+    }
+
+    /// Write the given value into the register, but only change the bits inside the mask.
+    pub fn write(&self, value: u32) {
+        let reg = PhysAddr::from(self.addr).to_virt().as_ptr::<u32>();
+
+        unsafe {
+            let value = match self.mask {
+                u32::MAX => value,
+                mask => (ptr::read_volatile(reg) & !mask) | (value & mask),
+            };
+            ptr::write_volatile(reg, value);
+        }
+    }
+}
@@ -0,0 +1,95 @@
+//! Information about the SBI implementation, which is collected at boot.
+//!
+//! Drivers use [`has`] to decide which SBI extensions they can use,
+//! instead of finding out by making calls that fail.
+
+use core::{
+    fmt,
+    sync::atomic::{AtomicU32, Ordering},
+};
+use sbi::probe::Extensions;
+
+pub use sbi::probe::Extension;
+
+/// The bits of all available [`Extensions`].
+static EXTENSIONS: AtomicU32 = AtomicU32::new(0);
+
+/// Check if the SBI implementation provides the given extension.
+///
+/// This always returns `false` until [`init`] was called.
+pub fn has(ext: Extension) -> bool {
+    available().contains(ext)
+}
+
+/// Return all extensions that are provided by the SBI implementation.
+pub fn available() -> Extensions {
+    Extensions::from_bits(EXTENSIONS.load(Ordering::Relaxed))
+}
+
+/// Probe all known extensions of the SBI implementation.
+///
+/// See [`sbi::probe::probe`] for how extensions are detected.
+pub fn init() {
+    EXTENSIONS.store(sbi::probe::probe().bits(), Ordering::Relaxed);
+}
+
+/// Log the name and version of the SBI implementation, and all available extensions.
+pub fn report() {
+    let (major, minor) = match sbi::base::spec_version() {
+        Ok(version) => version,
+        Err(_) => {
+            info!("{} legacy SBI v0.1 implementation", "Detected".green());
+            return;
+        }
+    };
+
+    let id = sbi::base::impl_id().ok();
+    let name = id.map_or("unknown", impl_name);
+    let version = sbi::base::impl_version().unwrap_or(0);
+
+    info!(
+        "{} SBI v{}.{} implementation {} v{}.{}",
+        "Detected".green(),
+        major,
+        minor,
+        name,
+        version >> 16,
+        version & 0xFFFF,
+    );
+    info!("SBI extensions: {}", ExtensionList);
+}
+
+/// Return the name of the SBI implementation with the given id.
+fn impl_name(id: usize) -> &'static str {
+    match id {
+        0 => "BBL",
+        1 => "OpenSBI",
+        2 => "Xvisor",
+        3 => "KVM",
+        4 => "RustSBI",
+        5 => "Diosix",
+        6 => "Coffer",
+        7 => "Xen Project",
+        8 => "PolarFire Hart Software Services",
+        9 => "coreboot",
+        10 => "oreboot",
+        sbi::interface::IMPL_ID => "Windy",
+        _ => "unknown",
+    }
+}
+
+/// Formats the names of all available extensions.
+struct ExtensionList;
+
+impl fmt::Display for ExtensionList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut available = available().iter();
+
+        match available.next() {
+            Some(first) => f.write_str(first.name())?,
+            None => return f.write_str("none"),
+        }
+
+        available.try_for_each(|ext| write!(f, ", {}", ext.name()))
+    }
+}
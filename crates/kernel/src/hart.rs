@@ -1,27 +1,288 @@
-//! Hart-Local storage
+//! Hart-Local storage and bring-up of secondary harts.
 
-use crate::pmem;
-use core::slice;
+use crate::{
+    firmware::{self, Extension},
+    page::VirtAddr,
+    pmem::{self, alloc::PAGE_SIZE},
+    time::Instant,
+    unit::KIB,
+};
+use core::{
+    cell::Cell,
+    slice,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+use devicetree::DeviceTree;
+use riscv::{csr::sscratch, symbols};
+use sbi::HartMask;
 
-/// Initialize the hart local storage.
+/// The maximum number of harts that are supported by the kernel.
+pub const MAX_HARTS: usize = 32;
+
+/// The size of the stack that is allocated for every secondary hart.
+pub const STACK_SIZE: usize = 64 * KIB;
+
+/// The time to wait for secondary harts to come online.
+const START_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[allow(clippy::declare_interior_mutable_const)]
+const NO_STACK: AtomicUsize = AtomicUsize::new(0);
+
+/// The top of the stack of every secondary hart, or `0` if the hart has no stack.
+static STACKS: [AtomicUsize; MAX_HARTS] = [NO_STACK; MAX_HARTS];
+
+/// Bitmap of all harts that finished their initialization.
+static ONLINE: AtomicUsize = AtomicUsize::new(0);
+
+displaydoc_lite::displaydoc! {
+    /// Errors that can happen while starting a secondary hart.
+    #[derive(Debug)]
+    pub enum Error {
+        /// the hart id exceeds the maximum number of supported harts
+        InvalidHartId,
+        /// {_0}
+        Alloc(pmem::AllocError),
+        /// {_0}
+        Sbi(sbi::Error),
+    }
+}
+
+/// The offset of the thread local storage, relative to the start of the [`HartLocal`] structure.
+const TLS_OFFSET: usize = 64;
+
+/// The offsets of the [`HartLocal`] fields that are used by the trap vector.
+pub(crate) const TLS_FIELD: usize = 0;
+pub(crate) const STACK_TOP_FIELD: usize = 8;
+pub(crate) const STACK_BOTTOM_FIELD: usize = 16;
+pub(crate) const SCRATCH_FIELD: usize = 24;
+
+/// The state that is private to every hart.
+///
+/// The structure is placed right in front of the thread local storage of the hart,
+/// and the `sscratch` register always points to it, so the trap vector can restore
+/// the `tp` register, and find the kernel stack, by reading `sscratch`.
+#[repr(C)]
+pub struct HartLocal {
+    /// The value of the `tp` register for this hart.
+    ///
+    /// This field must stay at [`TLS_FIELD`], because it's read by the trap vector.
+    tls: usize,
+    /// The top of the kernel stack of this hart.
+    ///
+    /// This field must stay at [`STACK_TOP_FIELD`], because it's read by the trap vector.
+    stack_top: usize,
+    /// The lowest address of the kernel stack of this hart.
+    ///
+    /// This field must stay at [`STACK_BOTTOM_FIELD`], because it's read by the trap vector.
+    stack_bottom: usize,
+    /// Space where the trap vector saves a register, while it's switching stacks.
+    ///
+    /// This field must stay at [`SCRATCH_FIELD`].
+    scratch: usize,
+    id: usize,
+    /// The number of interrupt handlers that are currently running on this hart.
+    interrupt_depth: Cell<usize>,
+}
+
+impl HartLocal {
+    /// The id of this hart.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// The bounds of the kernel stack of this hart, as `(bottom, top)`.
+    pub fn kernel_stack(&self) -> (usize, usize) {
+        (self.stack_bottom, self.stack_top)
+    }
+
+    /// The number of interrupt handlers that are currently running on this hart.
+    pub fn interrupt_depth(&self) -> usize {
+        self.interrupt_depth.get()
+    }
+
+    /// Check if this hart is currently handling an interrupt.
+    pub fn in_interrupt(&self) -> bool {
+        self.interrupt_depth() != 0
+    }
+
+    /// Run the given closure and count it as an interrupt handler.
+    pub(crate) fn interrupt<R>(&self, f: impl FnOnce() -> R) -> R {
+        self.interrupt_depth.set(self.interrupt_depth.get() + 1);
+        let result = f();
+        self.interrupt_depth.set(self.interrupt_depth.get() - 1);
+        result
+    }
+}
+
+/// Return the [`HartLocal`] structure of the hart that is executing this function.
+///
+/// # Panics
+///
+/// If [`init_hls`] was not called on this hart yet.
+pub fn current() -> &'static HartLocal {
+    try_current().expect("hart local storage is not initialized")
+}
+
+/// Return the [`HartLocal`] structure of the current hart, or `None`
+/// if [`init_hls`] was not called on this hart yet.
+pub fn try_current() -> Option<&'static HartLocal> {
+    match sscratch::read() {
+        0 => None,
+        ptr => Some(unsafe { &*(ptr as *const HartLocal) }),
+    }
+}
+
+/// Initialize the hart local storage, which consists of the [`HartLocal`] structure
+/// followed by a copy of the `.tdata` section and the zeroed `.tbss` section.
+///
+/// The kernel stack of a secondary hart is the one that was allocated when it was
+/// started, and the boot hart uses the stack of the kernel image.
 ///
 /// # Safety
 ///
 /// This function must be called on every hart, after the physical
 /// memory allocator is initialized.
-pub unsafe fn init_hls() -> Result<(), pmem::alloc::Error> {
-    let (start, end) = riscv::symbols::tdata_range();
-    let size = end as usize - start as usize;
-    let page_count = pmem::alloc::align_up(size, pmem::alloc::PAGE_SIZE) / pmem::alloc::PAGE_SIZE;
+pub unsafe fn init_hls(hart_id: usize) -> Result<(), pmem::alloc::Error> {
+    let (start, end) = symbols::tdata_range();
+    let tdata_size = end as usize - start as usize;
+    let (tbss_start, tbss_end) = symbols::tbss_range();
+    let tbss_size = tbss_end as usize - tbss_start as usize;
 
-    // allocate the new thread local storage and copy the original data
-    let orig = slice::from_raw_parts(start, size);
+    let size = TLS_OFFSET + tdata_size + tbss_size;
+    let page_count = pmem::alloc::align_up(size, PAGE_SIZE) / PAGE_SIZE;
+
+    // allocate the new thread local storage and copy the original data.
+    // the `.tbss` part is already zeroed by the allocator
+    let orig = slice::from_raw_parts(start, tdata_size);
     let mut new = pmem::zalloc_pages(page_count)?;
-    new.as_mut()[..size].copy_from_slice(orig);
+    new.as_mut()[TLS_OFFSET..TLS_OFFSET + tdata_size].copy_from_slice(orig);
+
+    let (stack_bottom, stack_top) = match STACKS.get(hart_id).map(|top| top.load(Ordering::Relaxed))
+    {
+        Some(top) if top != 0 => (top - STACK_SIZE, top),
+        _ => {
+            let (bottom, top) = symbols::stack_range();
+            (bottom as usize, top as usize)
+        }
+    };
 
-    // set the thread pointer
-    let tp = new.as_ref().as_ptr() as usize;
+    let local = new.as_mut_ptr().cast::<HartLocal>();
+    let tp = local as usize + TLS_OFFSET;
+    local.write(HartLocal {
+        tls: tp,
+        stack_top,
+        stack_bottom,
+        scratch: 0,
+        id: hart_id,
+        interrupt_depth: Cell::new(0),
+    });
+
+    // set the thread pointer, and make the structure available to the trap vector
     asm!("mv tp, {}", in(reg) tp);
+    sscratch::write(local as usize);
 
     Ok(())
 }
+
+/// Return the bounds of the stack, of any hart, that contains the given address.
+pub fn stack_range(addr: usize) -> Option<(usize, usize)> {
+    let (start, end) = symbols::stack_range();
+    let boot = (start as usize, end as usize);
+
+    let secondary = STACKS
+        .iter()
+        .map(|top| top.load(Ordering::Relaxed))
+        .filter(|&top| top != 0)
+        .map(|top| (top - STACK_SIZE, top));
+
+    core::iter::once(boot)
+        .chain(secondary)
+        .find(|&(start, end)| (start..=end).contains(&addr))
+}
+
+/// Start every hart that is listed inside the `/cpus` node, except the current one,
+/// and wait until they are online.
+pub fn start_secondaries(tree: &DeviceTree<'_>) {
+    if !firmware::has(Extension::Hsm) {
+        warn!("The SBI implementation doesn't support HSM, not starting other harts");
+        return;
+    }
+
+    let cpus = match tree.find_node("/cpus") {
+        Some(cpus) => cpus,
+        None => {
+            warn!("The devicetree has no `/cpus` node, not starting other harts");
+            return;
+        }
+    };
+
+    let mut started = 0;
+    for cpu in cpus.children() {
+        let is_cpu = cpu.prop("device_type").and_then(|prop| prop.as_str()) == Some("cpu");
+        let disabled = cpu
+            .prop("status")
+            .and_then(|prop| prop.as_str())
+            .map_or(false, |status| status != "okay" && status != "ok");
+
+        let hart = match cpu.prop("reg").and_then(|prop| prop.as_u32()) {
+            Some(hart) if is_cpu && !disabled => hart as usize,
+            _ => continue,
+        };
+
+        if hart == current().id() {
+            continue;
+        }
+
+        match start(hart) {
+            Ok(()) => started += 1,
+            Err(err) => warn!("Failed to start hart {}: {}", hart, err),
+        }
+    }
+
+    // the current hart is online too, so it's not counted
+    let secondaries = || online().iter().count() - 1;
+
+    let now = Instant::now();
+    while secondaries() < started && now.elapsed() < START_TIMEOUT {
+        core::hint::spin_loop();
+    }
+
+    info!(
+        "{} {} of {} secondary harts",
+        "Started".green(),
+        secondaries(),
+        started
+    );
+}
+
+/// Allocate a stack for the given hart and start it at the secondary entry point.
+fn start(hart: usize) -> Result<(), Error> {
+    let slot = STACKS.get(hart).ok_or(Error::InvalidHartId)?;
+
+    let stack = pmem::alloc_pages(STACK_SIZE / PAGE_SIZE).map_err(Error::Alloc)?;
+    let top = stack.as_mut_ptr() as usize + stack.len();
+    slot.store(top, Ordering::Relaxed);
+
+    // the hart starts with paging disabled, so it needs the physical address of the entry point
+    let entry = VirtAddr::from(crate::boot::_secondary_boot as usize)
+        .to_phys()
+        .unwrap();
+    sbi::hsm::start(hart, entry.into(), top).map_err(|err| {
+        slot.store(0, Ordering::Relaxed);
+        unsafe { pmem::dealloc_pages(stack.as_non_null_ptr(), STACK_SIZE / PAGE_SIZE) };
+        Error::Sbi(err)
+    })
+}
+
+/// Mark the current hart as online, after it finished its initialization.
+///
+/// Only online harts are able to receive messages from other harts.
+pub fn mark_online() {
+    ONLINE.fetch_or(1 << current().id(), Ordering::Release);
+}
+
+/// Return the mask of all harts that are online.
+pub fn online() -> HartMask {
+    HartMask::new(ONLINE.load(Ordering::Acquire), 0)
+}
@@ -0,0 +1,199 @@
+//! Inter-processor interrupts, which are used to run functions on other harts.
+//!
+//! Every hart has a queue of messages that is drained inside the handler
+//! of the supervisor software interrupt, which is raised using the SBI IPI extension.
+
+use crate::{arch, hart};
+use core::{
+    mem, ptr,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use riscv::{
+    csr::{sie, sip},
+    sync::Mutex,
+};
+use sbi::HartMask;
+
+/// The maximum number of messages that can be queued for a single hart.
+const QUEUE_SIZE: usize = 16;
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_QUEUE: Mutex<Queue> = Mutex::new(Queue::new());
+
+/// The message queue of every hart.
+static QUEUES: [Mutex<Queue>; hart::MAX_HARTS] = [EMPTY_QUEUE; hart::MAX_HARTS];
+
+displaydoc_lite::displaydoc! {
+    /// Errors that can happen while sending a message to another hart.
+    #[derive(Debug)]
+    pub enum Error {
+        /// the target hart is not online
+        Offline,
+    }
+}
+
+/// A function call that is sent to another hart.
+#[derive(Clone, Copy)]
+struct Message {
+    func: *const (dyn Fn() + Sync),
+    /// The number of harts that still have to execute the function,
+    /// or null if the sender doesn't wait for completion.
+    pending: *const AtomicUsize,
+}
+
+// SAFETY: the function is `Sync`, and the sender makes sure that
+// both pointers are valid until the message was handled.
+unsafe impl Send for Message {}
+
+/// A fixed size FIFO queue of messages.
+struct Queue {
+    messages: [Option<Message>; QUEUE_SIZE],
+    /// The index of the oldest message inside the queue.
+    head: usize,
+    len: usize,
+}
+
+impl Queue {
+    const fn new() -> Self {
+        Self {
+            messages: [None; QUEUE_SIZE],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// Append a message to the queue. Returns `false` if the queue is full.
+    fn push(&mut self, msg: Message) -> bool {
+        if self.len == QUEUE_SIZE {
+            return false;
+        }
+
+        self.messages[(self.head + self.len) % QUEUE_SIZE] = Some(msg);
+        self.len += 1;
+        true
+    }
+
+    /// Remove the oldest message from the queue.
+    fn pop(&mut self) -> Option<Message> {
+        let msg = self.messages[self.head].take()?;
+        self.head = (self.head + 1) % QUEUE_SIZE;
+        self.len -= 1;
+        Some(msg)
+    }
+}
+
+/// Enable software interrupts on the current hart, so it can receive messages.
+pub fn init_hart() {
+    sie::set_ssoft();
+}
+
+/// Run `f` on the given hart and wait until it returned.
+///
+/// If `hart` is the current hart, `f` is called directly.
+pub fn run_on(hart: usize, f: &(dyn Fn() + Sync)) -> Result<(), Error> {
+    call(HartMask::from_hart(hart), f, true)
+}
+
+/// Run `f` on every online hart, including the current one,
+/// and wait until it returned on all of them.
+pub fn run_on_all(f: &(dyn Fn() + Sync)) -> Result<(), Error> {
+    call(hart::online(), f, true)
+}
+
+/// Run `f` on every hart inside the mask, including the current one if it's
+/// part of the mask, and wait until it returned on all of them.
+pub fn run_on_mask(harts: HartMask, f: &(dyn Fn() + Sync)) -> Result<(), Error> {
+    call(harts, f, true)
+}
+
+/// Run `f` on the given hart, without waiting for completion.
+pub fn run_on_nowait(hart: usize, f: &'static (dyn Fn() + Sync)) -> Result<(), Error> {
+    call(HartMask::from_hart(hart), f, false)
+}
+
+/// Run `f` on every online hart, including the current one, without waiting for completion.
+pub fn run_on_all_nowait(f: &'static (dyn Fn() + Sync)) -> Result<(), Error> {
+    call(hart::online(), f, false)
+}
+
+/// Send `f` to all given harts, and call it directly if the current hart is part of `harts`.
+///
+/// If `wait` is `false`, `f` must live for `'static`, because it's used after this function returned.
+fn call(harts: HartMask, f: &(dyn Fn() + Sync), wait: bool) -> Result<(), Error> {
+    let online = hart::online();
+    if harts.iter().any(|hart| !online.contains(hart)) {
+        return Err(Error::Offline);
+    }
+
+    let current = hart::current().id();
+    let mut remote = harts;
+    remote.remove(current);
+
+    // SAFETY: the lifetime is erased, because either `f` is `'static`, or we wait
+    // until every hart executed it
+    let func = unsafe {
+        mem::transmute::<*const (dyn Fn() + Sync + '_), *const (dyn Fn() + Sync + 'static)>(f)
+    };
+
+    let pending = AtomicUsize::new(remote.iter().count());
+    let msg = Message {
+        func,
+        pending: if wait { &pending } else { ptr::null() },
+    };
+
+    if !remote.is_empty() {
+        remote.iter().for_each(|hart| push(hart, msg));
+
+        // the messages reference our stack, so we can't return an error after queueing them
+        sbi::ipi::send_ipi(remote).expect("failed to send inter-processor interrupt");
+    }
+
+    if harts.contains(current) {
+        f();
+    }
+
+    if wait {
+        while pending.load(Ordering::Acquire) != 0 {
+            // another hart may wait for us at the same time
+            handle_messages();
+            core::hint::spin_loop();
+        }
+    }
+
+    Ok(())
+}
+
+/// Put the message into the queue of the given hart, and wait
+/// until there's space if the queue is full.
+fn push(hart: usize, msg: Message) {
+    let queue = &QUEUES[hart];
+    while !arch::without_interrupts(|| queue.lock().push(msg)) {
+        handle_messages();
+        core::hint::spin_loop();
+    }
+}
+
+/// Execute all messages that are queued for the current hart.
+fn handle_messages() {
+    let queue = &QUEUES[hart::current().id()];
+
+    while let Some(msg) = arch::without_interrupts(|| queue.lock().pop()) {
+        unsafe {
+            (*msg.func)();
+
+            if let Some(pending) = msg.pending.as_ref() {
+                pending.fetch_sub(1, Ordering::Release);
+            }
+        }
+    }
+}
+
+/// Handle a supervisor software interrupt by executing all queued messages.
+///
+/// This function is called from the trap handler.
+pub fn handle_interrupt() {
+    // clear the interrupt first, so a message that arrives while
+    // draining the queue raises a new interrupt
+    sip::clear_ssoft();
+    handle_messages();
+}
@@ -0,0 +1,245 @@
+//! External interrupts, that are delivered through the PLIC.
+//!
+//! Drivers register a handler for their interrupt sources using [`register_irq`].
+//! The handler is executed inside the trap handler, with interrupts disabled,
+//! every time the source raises an interrupt.
+
+use crate::{arch, drivers::plic, hart, page::PhysAddr};
+use devicetree::{node::Node, DeviceTree, PHandle};
+use riscv::{csr::sie, sync::Mutex};
+
+/// The priority that is assigned to every interrupt source that has a handler.
+const DEFAULT_PRIORITY: u32 = 1;
+
+/// A function that handles the interrupt source that is passed as the argument.
+///
+/// Handlers run with interrupts disabled. Any lock they take must be taken with
+/// [`arch::without_interrupts`] by all other code too. The console lock already
+/// is, so handlers may log.
+pub type Handler = fn(u32);
+
+/// The global interrupt controller.
+static CONTROLLER: Mutex<Option<Controller>> = Mutex::new(None);
+
+/// The registered handler for every interrupt source.
+static HANDLERS: Mutex<[Option<Handler>; plic::MAX_SOURCES]> =
+    Mutex::new([None; plic::MAX_SOURCES]);
+
+displaydoc_lite::displaydoc! {
+    /// Errors that are related to external interrupts.
+    #[derive(Debug)]
+    pub enum Error {
+        /// the devicetree doesn't contain a compatible interrupt controller
+        NoController,
+        /// the interrupt controller node doesn't contain a `reg` property
+        NoRegisters,
+        /// the interrupt controller node doesn't contain a `riscv,ndev` property
+        NoSourceCount,
+        /// the interrupt controller has no supervisor context for this hart
+        NoContext,
+        /// the interrupt controller is not initialized
+        NotInitialized,
+        /// the interrupt source is not handled by the interrupt controller
+        InvalidIrq,
+        /// there's already a handler registered for the interrupt source
+        AlreadyRegistered,
+    }
+}
+
+struct Controller {
+    dev: plic::Device,
+    /// The phandle of the PLIC node, used to find the devices that are connected to it.
+    phandle: Option<PHandle>,
+    /// The supervisor mode context of every hart.
+    contexts: [Option<usize>; hart::MAX_HARTS],
+}
+
+impl Controller {
+    fn context(&self, hart: usize) -> Option<usize> {
+        self.contexts.get(hart).copied().flatten()
+    }
+}
+
+/// Initialize the PLIC that is found inside the devicetree and enable
+/// external interrupts on this hart.
+///
+/// All interrupt sources are disabled, until a handler is registered for them.
+pub fn init(tree: &DeviceTree<'_>) -> Result<(), Error> {
+    let node = tree
+        .find_compatible(plic::COMPATIBLE)
+        .next()
+        .ok_or(Error::NoController)?;
+
+    let base = node.regions().next().ok_or(Error::NoRegisters)?.start();
+    let sources = node
+        .prop("riscv,ndev")
+        .and_then(|prop| prop.as_u32())
+        .ok_or(Error::NoSourceCount)?
+        .min(plic::MAX_SOURCES as u32 - 1);
+
+    let dev = unsafe { plic::Device::new(PhysAddr::from(base).to_virt().into(), sources) };
+
+    // the `interrupts-extended` property contains a `(phandle, irq)` pair for every
+    // context, where the phandle points to the interrupt controller of a hart
+    let mut cells = node
+        .prop("interrupts-extended")
+        .map(|prop| prop.as_cells())
+        .into_iter()
+        .flatten();
+    let pairs = core::iter::from_fn(|| Some((cells.next()?, cells.next()?)));
+
+    let mut contexts = [None; hart::MAX_HARTS];
+    for (context, (intc, irq)) in pairs.enumerate() {
+        if irq != plic::SUPERVISOR_EXTERNAL {
+            continue;
+        }
+
+        let slot = hart_of(tree, intc.into()).and_then(|hart| contexts.get_mut(hart));
+        if let Some(slot) = slot {
+            *slot = Some(context);
+        }
+    }
+
+    // disable all sources, and accept every priority in every supervisor context
+    for irq in 1..=sources {
+        dev.set_priority(irq, 0);
+    }
+
+    for &context in contexts.iter().flatten() {
+        dev.set_threshold(context, 0);
+        for irq in 1..=sources {
+            dev.set_enabled(context, irq, false);
+        }
+    }
+
+    let controller = Controller {
+        dev,
+        phandle: node.phandle(),
+        contexts,
+    };
+
+    if controller.context(hart::current().id()).is_none() {
+        return Err(Error::NoContext);
+    }
+
+    arch::without_interrupts(|| *CONTROLLER.lock() = Some(controller));
+    sie::set_sext();
+
+    info!(
+        "{} PLIC with {} interrupt sources",
+        "Initialized".green(),
+        sources
+    );
+    Ok(())
+}
+
+/// Enable external interrupts on the current hart.
+///
+/// This must be called on every secondary hart, after [`init`] was called on the boot hart.
+pub fn init_hart() {
+    sie::set_sext();
+}
+
+/// Find the id of the hart that owns the interrupt controller with the given phandle.
+fn hart_of(tree: &DeviceTree<'_>, intc: PHandle) -> Option<usize> {
+    tree.find_node("/cpus")?
+        .children()
+        .find(|cpu| cpu.children().any(|child| child.phandle() == Some(intc)))
+        .and_then(|cpu| cpu.prop("reg")?.as_u32())
+        .map(|id| id as usize)
+}
+
+/// Return an iterator over all interrupt sources of the given device node,
+/// that are connected to the PLIC.
+///
+/// If the node has no `interrupt-parent` property, the one of the root node is used.
+pub fn sources<'tree>(
+    tree: &'tree DeviceTree<'tree>,
+    node: &Node<'tree>,
+) -> impl Iterator<Item = u32> + 'tree {
+    let parent = node
+        .interrupt_parent()
+        .or_else(|| tree.root().interrupt_parent());
+    let controller = arch::without_interrupts(|| CONTROLLER.lock().as_ref()?.phandle);
+
+    let connected = parent.is_some() && parent == controller;
+    node.interrupts().filter(move |_| connected)
+}
+
+/// Register a handler for the given interrupt source, and enable the source
+/// for the current hart.
+pub fn register_irq(irq: u32, handler: Handler) -> Result<(), Error> {
+    arch::without_interrupts(|| {
+        let controller = CONTROLLER.lock();
+        let controller = controller.as_ref().ok_or(Error::NotInitialized)?;
+
+        if irq == 0 || irq > controller.dev.sources() {
+            return Err(Error::InvalidIrq);
+        }
+        let context = controller
+            .context(hart::current().id())
+            .ok_or(Error::NoContext)?;
+
+        let mut handlers = HANDLERS.lock();
+        let slot = &mut handlers[irq as usize];
+        if slot.is_some() {
+            return Err(Error::AlreadyRegistered);
+        }
+        *slot = Some(handler);
+
+        controller.dev.set_priority(irq, DEFAULT_PRIORITY);
+        controller.dev.set_enabled(context, irq, true);
+        Ok(())
+    })
+}
+
+/// Disable the given interrupt source on all harts, and remove its handler.
+///
+/// Returns `true` if there was a handler for the source.
+pub fn unregister_irq(irq: u32) -> bool {
+    arch::without_interrupts(|| {
+        let controller = CONTROLLER.lock();
+        let controller = match controller.as_ref() {
+            Some(controller) if irq != 0 && irq <= controller.dev.sources() => controller,
+            _ => return false,
+        };
+
+        controller.dev.set_priority(irq, 0);
+        for &context in controller.contexts.iter().flatten() {
+            controller.dev.set_enabled(context, irq, false);
+        }
+
+        HANDLERS.lock()[irq as usize].take().is_some()
+    })
+}
+
+/// Handle a supervisor external interrupt by claiming all pending
+/// interrupt sources and running their handlers.
+///
+/// This function is called from the trap handler, and may log because
+/// the console lock can't be held by the interrupted code.
+pub fn handle_interrupt() {
+    let plic = CONTROLLER
+        .lock()
+        .as_ref()
+        .and_then(|controller| Some((controller.dev, controller.context(hart::current().id())?)));
+
+    let (dev, context) = match plic {
+        Some(plic) => plic,
+        None => {
+            warn!("External interrupt without an initialized interrupt controller");
+            return;
+        }
+    };
+
+    while let Some(irq) = dev.claim(context) {
+        let handler = HANDLERS.lock().get(irq as usize).copied().flatten();
+
+        match handler {
+            Some(handler) => handler(irq),
+            None => warn!("Unhandled interrupt source {}", irq),
+        }
+
+        dev.complete(context, irq);
+    }
+}
@@ -0,0 +1,140 @@
+//! The kernel symbol table, used to resolve addresses to function names.
+//!
+//! The table is written into the `.ksyms` section by `tools/ksyms.py`
+//! after the kernel was linked, which is done by the `tools/kernel-ld` linker wrapper.
+//! If the tool didn't run, every lookup fails, and [`init`] prints a warning.
+
+use crate::unit::KIB;
+use core::{fmt, mem, slice};
+use riscv::symbols;
+
+/// The magic bytes at the start of a valid symbol table.
+const MAGIC: &[u8] = b"KSYM";
+
+/// The size of the header, which contains the magic and the number of entries.
+const HEADER_SIZE: usize = 8;
+
+/// The number of bytes that are reserved for the symbol table.
+pub const TABLE_SIZE: usize = 256 * KIB;
+
+/// Reserves the space for the symbol table inside the kernel image.
+#[used]
+#[link_section = ".ksyms"]
+static TABLE_SPACE: [u8; TABLE_SIZE] = [0; TABLE_SIZE];
+
+/// A single entry inside the symbol table.
+#[repr(C)]
+struct Entry {
+    addr: u64,
+    size: u32,
+    name_off: u32,
+}
+
+/// A function symbol that was found inside the symbol table.
+#[derive(Debug, Clone, Copy)]
+pub struct Symbol {
+    name: &'static str,
+    addr: usize,
+    offset: usize,
+}
+
+impl Symbol {
+    /// The raw, mangled, name of this symbol.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The address where this symbol starts.
+    pub fn addr(&self) -> usize {
+        self.addr
+    }
+
+    /// The offset of the looked up address, relative to the start of this symbol.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:#}+{:#x}",
+            rustc_demangle::demangle(self.name),
+            self.offset
+        )
+    }
+}
+
+/// Check that a symbol table was embedded into the kernel, and warn if it's missing.
+pub fn init() {
+    if table().is_none() {
+        warn!("The kernel has no symbol table, backtraces won't contain function names");
+        warn!("Link the kernel using `tools/kernel-ld`, or run `tools/ksyms.py` on it");
+    }
+}
+
+/// Try to find the function that contains the given address.
+pub fn lookup(addr: usize) -> Option<Symbol> {
+    let (table, entries) = table()?;
+
+    // find the last entry that starts before, or at, `addr`
+    let idx = entries.partition_point(|entry| entry.addr as usize <= addr);
+    let entry = &entries[idx.checked_sub(1)?];
+
+    // a size of zero means that the size of the function is unknown
+    let start = entry.addr as usize;
+    if entry.size != 0 && addr >= start + entry.size as usize {
+        return None;
+    }
+
+    let name = table.get(entry.name_off as usize..)?;
+    let len = name.iter().position(|&x| x == 0)?;
+    let name = core::str::from_utf8(&name[..len]).ok()?;
+
+    Some(Symbol {
+        name,
+        addr: start,
+        offset: addr - start,
+    })
+}
+
+/// Return a formattable type that will print the symbol for the given address,
+/// or `<unknown>` if the address can't be resolved.
+pub fn resolve(addr: usize) -> impl fmt::Display {
+    Resolved(addr)
+}
+
+struct Resolved(usize);
+
+impl fmt::Display for Resolved {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match lookup(self.0) {
+            Some(sym) => sym.fmt(f),
+            None => f.write_str("<unknown>"),
+        }
+    }
+}
+
+/// Return the raw bytes of the symbol table, and the list of entries,
+/// if a valid table was embedded into the kernel.
+fn table() -> Option<(&'static [u8], &'static [Entry])> {
+    let (start, end) = symbols::ksyms_range();
+    let table = unsafe { slice::from_raw_parts(start as *const u8, end as usize - start as usize) };
+
+    if table.get(..MAGIC.len())? != MAGIC {
+        return None;
+    }
+
+    let mut count = [0u8; 4];
+    count.copy_from_slice(table.get(4..HEADER_SIZE)?);
+    let count = u32::from_le_bytes(count) as usize;
+
+    // make sure the entries don't exceed the table
+    if HEADER_SIZE + count * mem::size_of::<Entry>() > table.len() {
+        return None;
+    }
+
+    let entries = unsafe { slice::from_raw_parts(start.add(HEADER_SIZE).cast::<Entry>(), count) };
+    Some((table, entries))
+}
@@ -12,9 +12,12 @@
     slice_ptr_len,
     int_bits_const,
     array_map,
-    thread_local
+    thread_local,
+    alloc_error_handler
 )]
 
+extern crate alloc;
+
 #[cfg(not(target_pointer_width = "64"))]
 compile_error!("Windy can only run on 64 bit systems");
 #[cfg(not(target_has_atomic = "ptr"))]
@@ -26,9 +29,17 @@ pub mod console;
 pub mod drivers;
 #[macro_use]
 pub mod log;
+pub mod backtrace;
+pub mod firmware;
 pub mod hart;
+pub mod ipi;
+pub mod irq;
+pub mod ksyms;
 pub mod page;
+pub mod perf;
 pub mod pmem;
+pub mod time;
+pub mod timer;
 pub mod unit;
 
 mod boot;
@@ -38,6 +49,7 @@ mod trap;
 mod static_cell;
 pub use static_cell::StaticCell;
 
+use alloc::vec::Vec;
 use core::cell::Cell;
 use devicetree::DeviceTree;
 use displaydoc_lite::displaydoc;
@@ -60,17 +72,59 @@ fn kinit(hart_id: usize, tree: &DeviceTree<'_>) -> ! {
     }
 }
 
+/// The entry point for every secondary hart.
+fn kinit_secondary(hart_id: usize) -> ! {
+    unsafe { hart::init_hls(hart_id).expect("failed to initialize hart local storage") };
+    irq::init_hart();
+    ipi::init_hart();
+
+    hart::mark_online();
+    riscv::csr::sstatus::set_sie();
+
+    arch::wait_forever()
+}
+
 /// The "safe" entry point for the kernel.
-fn windy_main(_hart_id: usize, tree: &DeviceTree<'_>) -> Result<(), Error> {
+fn windy_main(hart_id: usize, tree: &DeviceTree<'_>) -> Result<(), Error> {
+    ksyms::init();
+
     // initialize hart local storage
-    unsafe { hart::init_hls().expect("failed to initialize hart local storage") };
+    unsafe { hart::init_hls(hart_id).expect("failed to initialize hart local storage") };
+    ipi::init_hart();
+
+    // the other harts are not running yet, so they can't allocate during the check
+    #[cfg(debug_assertions)]
+    if let Err(err) = page::table::self_check() {
+        panic!("page table self-check failed: {}", err);
+    }
+
+    #[cfg(feature = "bench")]
+    perf::bench::run();
+    hart::mark_online();
+
+    // initialize the interrupt controller, so drivers can register their interrupts
+    irq::init(tree).map_err(Error::Irq)?;
+
+    // the polled console is still usable if this fails, so it's not fatal
+    if let Err(err) = console::init_interrupts(tree) {
+        warn!("Failed to enable console interrupts: {}", err);
+    }
+
+    // bring up all other harts
+    hart::start_secondaries(tree);
+
+    // initialize the timer and start accepting interrupts
+    timer::init();
+    riscv::csr::sstatus::set_sie();
 
     let mut x = pmem::alloc_pages(4).unwrap();
     unsafe {
         x.as_mut()[0xFFF] = 1;
     }
 
-    for node in tree.find_nodes("/virtio_mmio") {
+    let nodes = tree.find_nodes("/virtio_mmio").collect::<Vec<_>>();
+    info!("Found {} virtio devices", nodes.len());
+    for node in nodes {
         info!("Tree node: {}", node.name());
     }
 
@@ -82,5 +136,7 @@ fn windy_main(_hart_id: usize, tree: &DeviceTree<'_>) -> Result<(), Error> {
 displaydoc! {
     /// Any error that will cause the kernel to exit.
     pub enum Error {
+        /// {_0}
+        Irq(irq::Error),
     }
 }
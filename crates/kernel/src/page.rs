@@ -1,43 +1,146 @@
 //! Implementation of the paging system.
+//!
+//! The kernel lives in the upper half of the address space, so the lower half is
+//! free for user space. The upper half contains two linear mappings:
+//!
+//! - The physmap, starting at [`PHYSMAP_BASE`], which maps physical memory,
+//!   so physical addresses can be accessed using [`PhysAddr::to_virt`].
+//! - The kernel image, which is linked at [`KERNEL_OFFSET`] plus the physical
+//!   address it's loaded at.
 
 mod types;
-pub use types::{PageSize, Perm, PhysAddr, VirtAddr};
+pub use types::{Mode, PageSize, Perm, PhysAddr, VirtAddr};
 
-pub mod sv39;
+pub mod table;
+use table::FreedTables;
 
+use crate::{
+    firmware::{self, Extension},
+    hart, ipi,
+    unit::GIB,
+};
+use core::sync::atomic::{AtomicU8, Ordering};
+use devicetree::DeviceTree;
 use riscv::csr::satp;
 
+/// The virtual address where the physmap starts.
+///
+/// This is the start of the upper half in Sv39, so it's inside the upper half in every mode.
+pub const PHYSMAP_BASE: usize = 0xFFFF_FFC0_0000_0000;
+
+/// The size of the physmap, which limits the physical addresses the kernel can access.
+pub const PHYSMAP_SIZE: usize = 128 * GIB;
+
+/// The difference between the virtual and the physical address of the kernel image.
+///
+/// Must be the same as `KERNEL_OFFSET` inside the linker script.
+pub const KERNEL_OFFSET: usize = 0xFFFF_FFFF_0000_0000;
+
 displaydoc_lite::displaydoc! {
     /// Errors that are related to paging.
     #[derive(Debug)]
     pub enum Error {
         /// tried to map an address that is not aligned to the page size
         UnalignedAddress,
+        /// tried to map a page size that is not supported by the addressing mode
+        UnsupportedPageSize,
         /// tried to identity map a range using a page size that can't fit into the range
         RangeTooSmall,
+        /// tried to use a range that exceeds the end of the address space
+        InvalidRange,
+        /// tried to map a page that is neither readable nor executable, or writable but not readable
+        InvalidPerm,
         /// tried to map an address which was already mapped
         AlreadyMapped,
+        /// tried to change an address which is not mapped
+        NotMapped,
         /// failed to allocate a new page
         Alloc(crate::pmem::AllocError),
+        /// {_0}
+        SelfCheck(&'static str),
+    }
+}
+
+/// The addressing mode of the kernel, as the number of levels of the page table.
+static LEVELS: AtomicU8 = AtomicU8::new(3);
+
+/// Return the addressing mode that is used by all page tables of the kernel.
+///
+/// This is Sv39, until the mode is changed using [`set_mode`].
+pub fn mode() -> Mode {
+    match LEVELS.load(Ordering::Relaxed) {
+        5 => Mode::Sv57,
+        4 => Mode::Sv48,
+        _ => Mode::Sv39,
     }
 }
 
+/// Change the addressing mode that is used by all page tables.
+///
+/// # Safety
+///
+/// There must be no page table, except for the boot table, that was created
+/// using the previous mode, and every hart must support the new mode.
+pub unsafe fn set_mode(mode: Mode) {
+    LEVELS.store(mode.levels() as u8, Ordering::Relaxed);
+}
+
+/// Find the largest addressing mode that is supported by every hart inside the devicetree.
+///
+/// Harts without a `mmu-type` property are assumed to support Sv39.
+pub fn detect_mode(tree: &DeviceTree<'_>) -> Mode {
+    let cpus = match tree.find_node("/cpus") {
+        Some(cpus) => cpus,
+        None => return Mode::Sv39,
+    };
+
+    cpus.children()
+        .filter(|cpu| cpu.prop("device_type").and_then(|prop| prop.as_str()) == Some("cpu"))
+        .map(|cpu| {
+            cpu.prop("mmu-type")
+                .and_then(|prop| prop.as_str())
+                .and_then(Mode::from_mmu_type)
+                .unwrap_or(Mode::Sv39)
+        })
+        .min_by_key(|mode| mode.levels())
+        .unwrap_or(Mode::Sv39)
+}
+
 /// Return a exclusive reference to the page table that
 /// the `satp` register points to.
-pub unsafe fn root() -> &'static mut sv39::Table {
-    let addr = satp::read().root_table;
-    &mut *(addr as usize as *mut _)
+pub unsafe fn root() -> &'static mut table::Table {
+    let addr = PhysAddr::from(satp::read().root_table as usize);
+    &mut *addr.to_virt().as_ptr()
 }
 
 /// Map the given address using the root page table.
+///
+/// If the address was already mapped, the old mapping is removed from the TLB of every hart.
 pub unsafe fn map(
     paddr: PhysAddr,
     vaddr: VirtAddr,
     size: PageSize,
     perm: Perm,
 ) -> Result<(), Error> {
+    let old = root().translate(vaddr);
     root().map(paddr, vaddr, size, perm)?;
-    riscv::asm::sfence(usize::from(vaddr), None);
+
+    match old {
+        Some((_, old_size)) => shootdown(vaddr, old_size),
+        None => riscv::asm::sfence(usize::from(vaddr), None),
+    }
+    Ok(())
+}
+
+/// Change the permissions of the page that contains the given address.
+pub unsafe fn protect(vaddr: VirtAddr, perm: Perm) -> Result<(), Error> {
+    let (_, size) = root().translate(vaddr).ok_or(Error::NotMapped)?;
+
+    let vaddr = VirtAddr::from(usize::from(vaddr) & !(size.size() - 1));
+    let (paddr, _) = root().translate(vaddr).ok_or(Error::NotMapped)?;
+
+    root().map(paddr, vaddr, size, perm)?;
+    shootdown(vaddr, size);
     Ok(())
 }
 
@@ -53,8 +156,153 @@ pub unsafe fn identity_map(
 
 /// Unmap the given virtual address. Returns `true` if the page was unmapped,
 /// `false` if there's no mapped entry at the given virt addr.
+///
+/// The mapping is removed from the TLB of every hart, and tables
+/// that became empty are freed afterwards.
 pub unsafe fn unmap(vaddr: VirtAddr) -> bool {
-    let res = root().unmap(vaddr);
-    riscv::asm::sfence(usize::from(vaddr), None);
+    let size = root().translate(vaddr).map(|(_, size)| size);
+    let mut freed = FreedTables::new();
+    let res = root().unmap(vaddr, &mut freed);
+
+    if let Some(size) = size {
+        shootdown_and_free(vaddr, size.size(), freed);
+    }
     res
 }
+
+/// Map a range of physical memory using the root page table.
+///
+/// See [`Table::map_range`](table::Table::map_range) for details.
+/// The range is invalidated inside the TLB of every hart, even if an error is returned.
+pub unsafe fn map_range(
+    vaddr: VirtAddr,
+    paddr: PhysAddr,
+    len: usize,
+    perm: Perm,
+) -> Result<(), Error> {
+    let mut freed = FreedTables::new();
+    let res = root().map_range(vaddr, paddr, len, perm, &mut freed);
+
+    // other harts may still use the old, invalid entries until they are fenced,
+    // and a page fault is always fatal for the kernel
+    shootdown_and_free(vaddr, len, freed);
+    res
+}
+
+/// Unmap a range using the root page table.
+///
+/// See [`Table::unmap_range`](table::Table::unmap_range) for details.
+/// The range is removed from the TLB of every hart, even if an error is returned,
+/// because a part of the range may already be unmapped. Tables that became empty
+/// are freed afterwards.
+pub unsafe fn unmap_range(vaddr: VirtAddr, len: usize) -> Result<(), Error> {
+    let mut freed = FreedTables::new();
+    let res = root().unmap_range(vaddr, len, &mut freed);
+    shootdown_and_free(vaddr, len, freed);
+    res
+}
+
+/// Change the permissions of a range using the root page table.
+///
+/// See [`Table::protect_range`](table::Table::protect_range) for details.
+/// The range is removed from the TLB of every hart, even if an error is returned,
+/// because a part of the range may already be changed.
+pub unsafe fn protect_range(vaddr: VirtAddr, len: usize, perm: Perm) -> Result<(), Error> {
+    let res = root().protect_range(vaddr, len, perm);
+    shootdown_range(vaddr, len);
+    res
+}
+
+/// Invalidate the page that contains `vaddr` inside the TLB of every online hart,
+/// because all harts share the same page table.
+///
+/// Other harts are flushed using the SBI RFENCE extension, or using an
+/// inter-processor interrupt if the extension is not available.
+pub fn shootdown(vaddr: VirtAddr, size: PageSize) {
+    let addr = usize::from(vaddr);
+    let flush = || riscv::asm::sfence(addr, None);
+    shootdown_with(addr, size.size(), &flush);
+}
+
+/// Invalidate every page inside the `len` bytes starting at `vaddr` inside the TLB
+/// of every online hart.
+///
+/// Large ranges flush the whole TLB, instead of every single page.
+pub fn shootdown_range(vaddr: VirtAddr, len: usize) {
+    let addr = usize::from(vaddr);
+    let flush = || flush_range(addr, len);
+    shootdown_with(addr, len, &flush);
+}
+
+/// Invalidate the whole TLB of every online hart.
+///
+/// Returns `false` if the TLB of any other hart couldn't be flushed.
+pub fn shootdown_all() -> bool {
+    let flush = || riscv::asm::sfence(None, None);
+    // a range of size `0` at address `0` flushes everything with the RFENCE extension
+    shootdown_with(0, 0, &flush)
+}
+
+/// Invalidate the range inside the TLB of every online hart, like [`shootdown_range`],
+/// and give the removed tables back to the page allocator afterwards.
+///
+/// An address-specific fence doesn't invalidate cached non-leaf entries,
+/// so the whole TLB is flushed if any table was removed. The tables are leaked
+/// if any hart couldn't be flushed, because it may still walk them.
+fn shootdown_and_free(vaddr: VirtAddr, len: usize, freed: FreedTables) {
+    if freed.is_empty() {
+        shootdown_range(vaddr, len);
+        return;
+    }
+
+    if !shootdown_all() {
+        warn!(
+            "Failed to flush the TLB of every hart, leaking {} page tables",
+            freed.len()
+        );
+        return;
+    }
+
+    // SAFETY: the TLB of every hart was flushed
+    unsafe { freed.free() };
+}
+
+/// The number of pages, up to which a range is flushed page by page,
+/// instead of flushing the whole TLB.
+const FLUSH_THRESHOLD: usize = 64;
+
+/// Invalidate every page inside the range in the TLB of the current hart.
+fn flush_range(addr: usize, len: usize) {
+    let size = PageSize::Kilopage.size();
+
+    if len / size > FLUSH_THRESHOLD {
+        riscv::asm::sfence(None, None);
+    } else {
+        let end = addr.saturating_add(len);
+        (addr..end)
+            .step_by(size)
+            .for_each(|page| riscv::asm::sfence(page, None));
+    }
+}
+
+/// Run `flush` on the current hart, and invalidate the range on all other online harts.
+///
+/// Returns `false` if the range couldn't be invalidated on any other hart.
+fn shootdown_with(addr: usize, len: usize, flush: &(dyn Fn() + Sync)) -> bool {
+    flush();
+
+    let mut remote = hart::online();
+    if let Some(local) = hart::try_current() {
+        remote.remove(local.id());
+    }
+
+    if remote.is_empty() {
+        return true;
+    }
+
+    let fenced = firmware::has(Extension::Rfence)
+        && sbi::rfence::remote_sfence_vma(remote, addr, len).is_ok();
+
+    // the harts were online a moment ago, and harts never go offline
+    fenced || ipi::run_on_mask(remote, flush).is_ok()
+}
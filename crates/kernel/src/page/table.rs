@@ -0,0 +1,937 @@
+//! Implementation of the page table, that is shared by the Sv39, Sv48 and Sv57
+//! addressing modes.
+//!
+//! All modes use the same table format, and only differ in the number of levels,
+//! which is given by the [addressing mode](super::mode) of the kernel.
+
+use super::{Error, PageSize, Perm, PhysAddr, VirtAddr};
+use crate::pmem::{self, alloc::PAGE_SIZE, LinkedList};
+use core::ptr::NonNull;
+
+/// The central page table structure.
+#[repr(C, align(4096))]
+pub struct Table {
+    entries: [Entry; 512],
+}
+
+impl Table {
+    /// Create a new, empty table.
+    pub const fn new() -> Self {
+        Self {
+            entries: [Entry::EMPTY; 512],
+        }
+    }
+
+    /// Map a page with the given page size, from the given virtual address,
+    /// to the physical address. The newly mapped page will have the given permissions.
+    ///
+    /// Note that this method will overwrite any pre-existing mapping for the given address.
+    pub fn map(
+        &mut self,
+        paddr: PhysAddr,
+        vaddr: VirtAddr,
+        size: PageSize,
+        perm: Perm,
+    ) -> Result<(), Error> {
+        // check if the addresses are aligned
+        if !size.is_aligned(paddr.into()) || !size.is_aligned(vaddr.into()) {
+            return Err(Error::UnalignedAddress);
+        }
+
+        let mode = super::mode();
+        if !mode.supports(size) {
+            return Err(Error::UnsupportedPageSize);
+        }
+
+        let ppn = ppn_of_paddr(paddr);
+
+        // walk down to the level where the page is mapped
+        let mut table = self;
+        for level in (size.level() + 1..mode.levels()).rev() {
+            table = get_next_level(&mut table.entries[vpn(vaddr, level)])?;
+        }
+        let entry = &mut table.entries[vpn(vaddr, size.level())];
+
+        let new_entry = (ppn << 10) | (usize::from(perm) << 1) | Entry::VALID as usize;
+        entry.set(new_entry as u64);
+        Ok(())
+    }
+
+    /// Identity map the given range using `size` pages.
+    pub fn identity_map(
+        &mut self,
+        start: PhysAddr,
+        end: PhysAddr,
+        perm: Perm,
+        size: PageSize,
+    ) -> Result<(), Error> {
+        let (start, end) = (usize::from(start), usize::from(end));
+
+        if end - start < size.size() {
+            return Err(Error::RangeTooSmall);
+        }
+
+        for addr in (start..end).step_by(size.size()) {
+            let vaddr = VirtAddr::from(addr);
+            let paddr = PhysAddr::from(addr);
+            riscv::asm::sfence(usize::from(vaddr), None);
+            self.map(paddr, vaddr, size, perm)?;
+        }
+
+        Ok(())
+    }
+
+    /// Identity map a region using the best fitting page size.
+    pub fn fit_identity_map(
+        &mut self,
+        start: PhysAddr,
+        end: PhysAddr,
+        perm: Perm,
+    ) -> Result<(), Error> {
+        self.fit_map(start, end, VirtAddr::from(usize::from(start)), perm)
+    }
+
+    /// Map the physical region to the virtual addresses starting at `vaddr`,
+    /// using the best fitting page size.
+    ///
+    /// The end of the region is rounded up to the next page. See [`Self::map_range`].
+    ///
+    /// Tables that are removed on error are freed right away, so this must only be
+    /// used to build tables that are not in use by any hart yet.
+    pub fn fit_map(
+        &mut self,
+        start: PhysAddr,
+        end: PhysAddr,
+        vaddr: VirtAddr,
+        perm: Perm,
+    ) -> Result<(), Error> {
+        let len = usize::from(end).saturating_sub(start.into());
+        let len = pmem::alloc::align_up(len, PageSize::Kilopage.size());
+
+        let mut freed = FreedTables::new();
+        let res = self.map_range(vaddr, start, len, perm, &mut freed);
+        // SAFETY: the table is not in use yet, see above
+        unsafe { freed.free() };
+        res
+    }
+
+    /// Map `len` bytes, starting at the physical address `paddr`, to the virtual
+    /// addresses starting at `vaddr`.
+    ///
+    /// Every part of the range is mapped using the largest page size that fits,
+    /// so `vaddr` and `paddr` should have the same offset inside a large page.
+    ///
+    /// Fails with [`Error::AlreadyMapped`] if any page inside the range is already
+    /// mapped, and the table is left unchanged if an error is returned.
+    /// Tables that are removed while undoing the changes are added to `freed`.
+    /// The TLB is not flushed.
+    pub fn map_range(
+        &mut self,
+        vaddr: VirtAddr,
+        paddr: PhysAddr,
+        len: usize,
+        perm: Perm,
+        freed: &mut FreedTables,
+    ) -> Result<(), Error> {
+        let (vstart, pstart) = (usize::from(vaddr), usize::from(paddr));
+        check_range(vstart, len)?;
+        check_range(pstart, len)?;
+        check_perm(perm)?;
+
+        let mut off = 0;
+        while off < len {
+            let (vaddr, paddr) = (vstart + off, pstart + off);
+            let size = best_size(vaddr, paddr, len - off);
+
+            if let Err(err) = self.map_new(paddr.into(), vaddr.into(), size, perm) {
+                // remove everything that was mapped by this call, and the tables
+                // that were allocated before the failing page could be mapped
+                let _ = self.unmap_range(vstart.into(), off, freed);
+                self.prune_at(vaddr.into(), super::mode().levels() - 1, freed);
+                return Err(err);
+            }
+
+            off += size.size();
+        }
+
+        Ok(())
+    }
+
+    /// Map a single page, but fail if there's any mapping at the address.
+    fn map_new(
+        &mut self,
+        paddr: PhysAddr,
+        vaddr: VirtAddr,
+        size: PageSize,
+        perm: Perm,
+    ) -> Result<(), Error> {
+        match self.slot(vaddr) {
+            Slot::Empty(level) if level >= size.level() => self.map(paddr, vaddr, size, perm),
+            _ => Err(Error::AlreadyMapped),
+        }
+    }
+
+    /// Unmap every page inside the `len` bytes that start at `vaddr`.
+    ///
+    /// Large pages that are only partially inside the range are split, and
+    /// unmapped parts of the range are skipped. Empty tables are removed like in
+    /// [`Self::unmap`]. The TLB is not flushed.
+    pub fn unmap_range(
+        &mut self,
+        vaddr: VirtAddr,
+        len: usize,
+        freed: &mut FreedTables,
+    ) -> Result<(), Error> {
+        let start = usize::from(vaddr);
+        let end = check_range(start, len)?;
+
+        let mut addr = start;
+        while addr < end {
+            match self.slot(addr.into()) {
+                Slot::Empty(level) => addr = next_page(addr, level),
+                Slot::Leaf(level) => {
+                    let page = align_down(addr, level_size(level));
+                    if page < start || next_page(addr, level) > end {
+                        self.split(addr.into())?;
+                        continue;
+                    }
+
+                    self.unmap(page.into(), freed);
+                    addr = next_page(addr, level);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Change the permissions of every page inside the `len` bytes that start at `vaddr`.
+    ///
+    /// Large pages that are only partially inside the range are split, before any
+    /// permission is changed. Fails with [`Error::NotMapped`], without changing anything,
+    /// if a part of the range is not mapped. If splitting fails, no permission is changed,
+    /// but the pages at the ends may already be split into smaller pages with the same
+    /// mapping. The TLB is not flushed.
+    pub fn protect_range(&mut self, vaddr: VirtAddr, len: usize, perm: Perm) -> Result<(), Error> {
+        let start = usize::from(vaddr);
+        let end = check_range(start, len)?;
+        check_perm(perm)?;
+
+        // make sure the whole range is mapped, before anything is changed
+        let mut addr = start;
+        while addr < end {
+            match self.slot(addr.into()) {
+                Slot::Empty(_) => return Err(Error::NotMapped),
+                Slot::Leaf(level) => addr = next_page(addr, level),
+            }
+        }
+
+        // only the pages at both ends can be partially inside the range
+        if len > 0 {
+            self.split_inside(start, start, end)?;
+            self.split_inside(end - 1, start, end)?;
+        }
+
+        let mut addr = start;
+        while addr < end {
+            let (entry, level) = self.leaf_mut(addr.into()).ok_or(Error::NotMapped)?;
+            let perm_bits = (u8::from(perm) as u64) << 1;
+            entry.set((entry.get() & !Entry::PERM_MASK) | perm_bits);
+            addr = next_page(addr, level);
+        }
+
+        Ok(())
+    }
+
+    /// Split the page that contains `addr`, until it's completely inside `start..end`.
+    fn split_inside(&mut self, addr: usize, start: usize, end: usize) -> Result<(), Error> {
+        loop {
+            let level = match self.slot(addr.into()) {
+                Slot::Leaf(level) => level,
+                Slot::Empty(_) => return Err(Error::NotMapped),
+            };
+
+            let page = align_down(addr, level_size(level));
+            if page >= start && next_page(addr, level) <= end {
+                return Ok(());
+            }
+
+            self.split(addr.into())?;
+        }
+    }
+
+    /// Split the large page that contains the given address into a table
+    /// of pages with the next smaller size, that have the same permissions.
+    fn split(&mut self, vaddr: VirtAddr) -> Result<(), Error> {
+        let (entry, level) = self.leaf_mut(vaddr).ok_or(Error::NotMapped)?;
+        if level == 0 {
+            return Ok(());
+        }
+
+        let (table, table_paddr) = alloc_table()?;
+        let base = usize::from(entry.ppn());
+        let flags = entry.get() & Entry::FLAGS_MASK;
+
+        for (idx, child) in table.entries.iter_mut().enumerate() {
+            let paddr = PhysAddr::from(base + idx * level_size(level - 1));
+            child.set(((ppn_of_paddr(paddr) as u64) << 10) | flags);
+        }
+
+        entry.set(((ppn_of_paddr(table_paddr) as u64) << 10) | Entry::VALID);
+        Ok(())
+    }
+
+    /// Tries to unmap the given virtual address.
+    ///
+    /// Every table below this one, that is empty after removing the mapping, is
+    /// removed and added to `freed`. This table itself is never removed, even if it's empty.
+    /// The TLB is not flushed.
+    ///
+    /// Return `true` if the unmapping was successful.
+    pub fn unmap(&mut self, vaddr: VirtAddr, freed: &mut FreedTables) -> bool {
+        self.unmap_at(vaddr, super::mode().levels() - 1, freed)
+    }
+
+    /// Unmap the given virtual address, where this table is at the given level.
+    fn unmap_at(&mut self, vaddr: VirtAddr, level: usize, freed: &mut FreedTables) -> bool {
+        let entry = &mut self.entries[vpn(vaddr, level)];
+
+        match entry.kind() {
+            Some(EntryKind::Leaf) => {
+                // clear the entry to unmap the virtaddr.
+                entry.set(0);
+                true
+            }
+            Some(EntryKind::Branch(next)) if level > 0 => {
+                let table = unsafe { &mut *next.to_virt().as_ptr::<Table>() };
+                let unmapped = table.unmap_at(vaddr, level - 1, freed);
+
+                // the tables below were already removed if they became empty,
+                // so only the next table has to be checked
+                if unmapped && table.is_empty() {
+                    entry.set(0);
+                    unsafe { freed.push(table) };
+                }
+
+                unmapped
+            }
+            _ => false,
+        }
+    }
+
+    /// Remove every table on the walk for the given address, that has no valid entry,
+    /// where this table is at the given level.
+    fn prune_at(&mut self, vaddr: VirtAddr, level: usize, freed: &mut FreedTables) {
+        let entry = &mut self.entries[vpn(vaddr, level)];
+
+        if let (Some(EntryKind::Branch(next)), true) = (entry.kind(), level > 0) {
+            let table = unsafe { &mut *next.to_virt().as_ptr::<Table>() };
+            table.prune_at(vaddr, level - 1, freed);
+
+            if table.is_empty() {
+                entry.set(0);
+                unsafe { freed.push(table) };
+            }
+        }
+    }
+
+    /// Unmap everything, and free every table below this one.
+    ///
+    /// This table itself is not freed, because the root table may be a static.
+    /// A root table that was allocated must be freed by the caller afterwards.
+    ///
+    /// # Safety
+    ///
+    /// The tables below this one must not be used by any other table, and this table
+    /// must not be in use by any hart. No hart may have cached entries of this table
+    /// inside its TLB, because the tables are freed right away.
+    pub unsafe fn destroy(&mut self) {
+        self.destroy_at(super::mode().levels() - 1);
+    }
+
+    unsafe fn destroy_at(&mut self, level: usize) {
+        for entry in self.entries.iter_mut() {
+            if let (Some(EntryKind::Branch(next)), true) = (entry.kind(), level > 0) {
+                let table = &mut *next.to_virt().as_ptr::<Table>();
+                table.destroy_at(level - 1);
+                free_table(table);
+            }
+
+            entry.set(0);
+        }
+    }
+
+    /// Check if this table has no valid entry.
+    pub fn is_empty(&self) -> bool {
+        self.entries.iter().all(|entry| !entry.valid())
+    }
+
+    /// Try to tranlsate the given virtual address, to their physical address,
+    /// as mapped inside this table.
+    pub fn translate(&self, vaddr: VirtAddr) -> Option<(PhysAddr, PageSize)> {
+        self.entry(vaddr).map(|(_, entry, size)| {
+            // extract the offset inside the page
+            let off = usize::from(vaddr) & (size.size() - 1);
+            let ppn = entry.ppn();
+
+            (ppn.offset(off), size)
+        })
+    }
+
+    /// Find the level where the table walk for the given address ends.
+    fn slot(&self, vaddr: VirtAddr) -> Slot {
+        let mut table = self;
+
+        for level in (0..super::mode().levels()).rev() {
+            match table.entries[vpn(vaddr, level)].kind() {
+                None => return Slot::Empty(level),
+                Some(EntryKind::Leaf) => return Slot::Leaf(level),
+                Some(EntryKind::Branch(next)) => {
+                    table = unsafe { &*next.to_virt().as_ptr::<Table>() }
+                }
+            }
+        }
+
+        // a branch at the last level is invalid, so it's treated as not mapped
+        Slot::Empty(0)
+    }
+
+    /// Return the leaf entry that maps the given address, and its level.
+    fn leaf_mut(&mut self, vaddr: VirtAddr) -> Option<(&mut Entry, usize)> {
+        let mut table = self;
+
+        for level in (0..super::mode().levels()).rev() {
+            let idx = vpn(vaddr, level);
+            match table.entries[idx].kind()? {
+                EntryKind::Leaf => return Some((&mut table.entries[idx], level)),
+                EntryKind::Branch(next) => {
+                    table = unsafe { &mut *next.to_virt().as_ptr::<Table>() }
+                }
+            }
+        }
+
+        None
+    }
+
+    fn entry(&self, vaddr: VirtAddr) -> Option<(&Table, &Entry, PageSize)> {
+        let mut table = self;
+
+        for level in (0..super::mode().levels()).rev() {
+            let entry = &table.entries[vpn(vaddr, level)];
+            match entry.kind()? {
+                EntryKind::Leaf => return Some((table, entry, PageSize::from_level(level)?)),
+                EntryKind::Branch(next) => table = unsafe { &*next.to_virt().as_ptr::<Table>() },
+            }
+        }
+
+        None
+    }
+}
+
+/// Give a table, that was allocated by [`get_next_level`], back to the page allocator.
+unsafe fn free_table(table: &mut Table) {
+    pmem::dealloc(NonNull::from(table).cast());
+}
+
+/// Check that unmapping frees exactly the tables that became empty, without changing
+/// any other entry, that [`Table::destroy`] frees every table of a tree, that large pages
+/// are split correctly, and that a failed [`Table::map_range`] is undone completely.
+///
+/// The check uses its own tables, that are never used by any hart.
+/// Tables may be leaked if the check fails.
+pub fn self_check() -> Result<(), Error> {
+    let free_before = pmem::alloc_stats().free;
+
+    with_check_table(check_unmap)?;
+    with_check_table(check_destroy)?;
+    with_check_table(check_split_ends)?;
+    with_check_table(check_map_undo)?;
+
+    ensure(
+        pmem::alloc_stats().free == free_before,
+        "page tables were leaked",
+    )
+}
+
+/// Run a check of the [`self_check`] on a new root table, and free all tables afterwards.
+fn with_check_table(check: fn(&mut Table) -> Result<(), Error>) -> Result<(), Error> {
+    let (root, _) = alloc_table()?;
+    let res = check(root);
+
+    unsafe {
+        // SAFETY: no hart ever used the table
+        root.destroy();
+        free_table(root);
+    }
+
+    res
+}
+
+/// Fail the [`self_check`] with the given message, if `cond` is `false`.
+fn ensure(cond: bool, msg: &'static str) -> Result<(), Error> {
+    if cond {
+        Ok(())
+    } else {
+        Err(Error::SelfCheck(msg))
+    }
+}
+
+/// Return the raw value of every entry of the table.
+fn snapshot(table: &Table) -> [u64; 512] {
+    let mut raw = [0; 512];
+    raw.iter_mut()
+        .zip(table.entries.iter())
+        .for_each(|(raw, entry)| *raw = entry.get());
+    raw
+}
+
+fn check_unmap(root: &mut Table) -> Result<(), Error> {
+    let levels = super::mode().levels();
+    let perm = Perm::READ | Perm::WRITE;
+    let paddr = PhysAddr::from(0x8000_0000);
+
+    // `other` uses the first entry of the root table, and `target` and `neighbour`
+    // share all tables of the second entry, except for the last level
+    let other = VirtAddr::from(0);
+    let target = VirtAddr::from(level_size(levels - 1));
+    let neighbour = VirtAddr::from(level_size(levels - 1) + level_size(1));
+
+    root.map(paddr, other, PageSize::Kilopage, perm)?;
+    let root_before = snapshot(root);
+    let other_entry = root.entry(other).map(|(_, entry, _)| entry.get());
+
+    root.map(paddr, target, PageSize::Kilopage, perm)?;
+    root.map(paddr, neighbour, PageSize::Kilopage, perm)?;
+    let free_mapped = pmem::alloc_stats().free;
+
+    // only the last table of `target` is empty after unmapping it
+    let mut freed = FreedTables::new();
+    ensure(root.unmap(target, &mut freed), "failed to unmap a page")?;
+    ensure(freed.len() == 1, "unmapping removed the wrong tables")?;
+    ensure(
+        root.translate(target).is_none(),
+        "unmapped page is still mapped",
+    )?;
+    ensure(
+        root.translate(neighbour).is_some(),
+        "unmapping removed a neighbour",
+    )?;
+
+    // now every table of the second entry is empty
+    ensure(root.unmap(neighbour, &mut freed), "failed to unmap a page")?;
+    ensure(freed.len() == levels, "unmapping removed the wrong tables")?;
+    ensure(
+        root.translate(neighbour).is_none(),
+        "unmapped page is still mapped",
+    )?;
+
+    ensure(
+        snapshot(root) == root_before,
+        "unmapping changed unrelated entries of the root table",
+    )?;
+    ensure(
+        root.entry(other).map(|(_, entry, _)| entry.get()) == other_entry,
+        "unmapping changed an unrelated page",
+    )?;
+
+    // the removed tables must stay allocated until the TLB of every hart was flushed
+    ensure(
+        pmem::alloc_stats().free == free_mapped,
+        "removed tables were freed before the TLB was flushed",
+    )?;
+
+    // SAFETY: no hart ever used the table
+    unsafe { freed.free() };
+    ensure(
+        pmem::alloc_stats().free == free_mapped + levels * PAGE_SIZE,
+        "removed tables were not freed",
+    )
+}
+
+fn check_destroy(root: &mut Table) -> Result<(), Error> {
+    let levels = super::mode().levels();
+    let perm = Perm::READ | Perm::EXEC;
+    let paddr = PhysAddr::from(0x8000_0000);
+    let free_before = pmem::alloc_stats().free;
+
+    // build a tree that has branches at every level, next to large pages
+    let top = level_size(levels - 1);
+    root.map(paddr, VirtAddr::from(0), PageSize::Kilopage, perm)?;
+    root.map(paddr, VirtAddr::from(top), PageSize::Kilopage, perm)?;
+    root.map(
+        paddr,
+        VirtAddr::from(top + level_size(1)),
+        PageSize::Kilopage,
+        perm,
+    )?;
+    root.map(paddr, VirtAddr::from(2 * top), PageSize::Megapage, perm)?;
+    root.map(paddr, VirtAddr::from(3 * top), PageSize::Gigapage, perm)?;
+
+    // the two pages at `top` share every table, except for the last one
+    let tables = (levels - 1) + levels + (levels - 2) + (levels - 3);
+    ensure(
+        free_before - pmem::alloc_stats().free == tables * PAGE_SIZE,
+        "mapping allocated the wrong number of tables",
+    )?;
+
+    // SAFETY: no hart ever used the table
+    unsafe { root.destroy() };
+    ensure(root.is_empty(), "destroyed table still has entries")?;
+    ensure(
+        pmem::alloc_stats().free == free_before,
+        "destroying the table leaked tables",
+    )
+}
+
+fn check_split_ends(root: &mut Table) -> Result<(), Error> {
+    let mega = level_size(1);
+    let paddr = 0x8000_0000;
+    let vaddr = level_size(super::mode().levels() - 1);
+    let perm = Perm::READ | Perm::WRITE;
+
+    // four megapages, where the first two are protected and the last two are unmapped,
+    // using ranges that start and end in the middle of a megapage
+    let mut freed = FreedTables::new();
+    root.map_range(vaddr.into(), paddr.into(), 4 * mega, perm, &mut freed)?;
+    let range = |base: usize| base + mega / 2..base + mega + mega / 2;
+    let (protected, unmapped) = (range(vaddr), range(vaddr + 2 * mega));
+
+    root.protect_range(protected.start.into(), mega, Perm::READ)?;
+    root.unmap_range(unmapped.start.into(), mega, &mut freed)?;
+
+    for addr in (vaddr..vaddr + 4 * mega).step_by(PAGE_SIZE) {
+        let expected = if protected.contains(&addr) {
+            Perm::READ
+        } else {
+            perm
+        };
+        let split = root.entry(addr.into()).map_or(false, |(_, entry, size)| {
+            size == PageSize::Kilopage
+                && entry.perm() == expected
+                && entry.ppn() == PhysAddr::from(paddr + (addr - vaddr))
+        });
+        let correct = if unmapped.contains(&addr) {
+            root.translate(addr.into()).is_none()
+        } else {
+            split
+        };
+        ensure(correct, "splitting a large page changed its mapping")?;
+    }
+
+    // SAFETY: no hart ever used the table
+    unsafe { freed.free() };
+    Ok(())
+}
+
+fn check_map_undo(root: &mut Table) -> Result<(), Error> {
+    let levels = super::mode().levels();
+    let perm = Perm::READ | Perm::WRITE;
+    let paddr = PhysAddr::from(0x8000_0000);
+
+    // the range starts below the second entry of the root table, whose tables must be
+    // removed again, and ends with a page below the third entry, that is already mapped
+    let top = 2 * level_size(levels - 1);
+    let start = top - 2 * level_size(1);
+    let mapped = VirtAddr::from(top + PAGE_SIZE);
+
+    root.map(paddr, mapped, PageSize::Kilopage, perm)?;
+    let root_before = snapshot(root);
+    let mapped_entry = root.entry(mapped).map(|(_, entry, _)| entry.get());
+    let free_before = pmem::alloc_stats().free;
+
+    let mut freed = FreedTables::new();
+    let len = top + 2 * PAGE_SIZE - start;
+    let res = root.map_range(start.into(), paddr, len, perm, &mut freed);
+    ensure(
+        matches!(res, Err(Error::AlreadyMapped)),
+        "mapping over a page didn't fail",
+    )?;
+    ensure(
+        freed.len() == levels - 2,
+        "undoing the mapping didn't remove its tables",
+    )?;
+
+    // SAFETY: no hart ever used the table
+    unsafe { freed.free() };
+
+    ensure(
+        root.translate(start.into()).is_none() && root.translate(top.into()).is_none(),
+        "undoing the mapping left pages mapped",
+    )?;
+    ensure(
+        snapshot(root) == root_before,
+        "undoing the mapping changed the root table",
+    )?;
+    ensure(
+        root.entry(mapped).map(|(_, entry, _)| entry.get()) == mapped_entry,
+        "undoing the mapping changed the page that was already mapped",
+    )?;
+    ensure(
+        pmem::alloc_stats().free == free_before,
+        "undoing the mapping leaked tables",
+    )
+}
+
+/// Tables that were removed from a page table, but can't be freed yet.
+///
+/// A removed table may still be used by other harts, because an address-specific
+/// `sfence.vma` doesn't invalidate cached non-leaf entries. The tables must only be
+/// given back to the page allocator after the whole TLB of every hart was flushed.
+///
+/// Tables that are dropped without calling [`Self::free`] are leaked.
+#[must_use]
+pub struct FreedTables {
+    list: LinkedList,
+    count: usize,
+}
+
+impl FreedTables {
+    /// Create an empty list of tables.
+    pub const fn new() -> Self {
+        Self {
+            list: LinkedList::new(),
+            count: 0,
+        }
+    }
+
+    /// Check if no table was removed.
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    /// Return the number of tables in this list.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Add a table that was removed from its parent.
+    ///
+    /// The list is linked through the first entry of every table. The link is a
+    /// page aligned pointer, so the entry stays invalid for a hart that still walks the table.
+    unsafe fn push(&mut self, table: &mut Table) {
+        self.list.push(NonNull::from(table).cast());
+        self.count += 1;
+    }
+
+    /// Give all tables back to the page allocator.
+    ///
+    /// # Safety
+    ///
+    /// No hart may have cached entries of the tables inside its TLB,
+    /// so the whole TLB of every hart must be flushed before.
+    pub unsafe fn free(mut self) {
+        while let Some(table) = self.list.pop() {
+            pmem::dealloc(table.cast());
+        }
+    }
+}
+
+/// Allocate a new, empty table, and return it together with its physical address.
+fn alloc_table() -> Result<(&'static mut Table, PhysAddr), Error> {
+    let page = pmem::zalloc()
+        .map_err(Error::Alloc)?
+        .as_mut_ptr()
+        .cast::<Table>();
+
+    // the table lives inside the physmap, like every allocated page
+    let paddr = VirtAddr::from(page)
+        .to_phys()
+        .expect("page table was allocated outside of the physmap");
+
+    Ok((unsafe { &mut *page }, paddr))
+}
+
+/// Returns `None` if the given entry is a leaf.
+fn get_next_level(entry: &mut Entry) -> Result<&mut Table, Error> {
+    match entry.kind() {
+        None => {
+            let (table, paddr) = alloc_table()?;
+
+            // make the given entry show to the new table
+            let ppn = ppn_of_paddr(paddr) as u64;
+            entry.set((ppn << 10) | Entry::VALID);
+
+            Ok(table)
+        }
+        Some(EntryKind::Branch(next)) => Ok(unsafe { &mut *next.to_virt().as_ptr() }),
+        Some(EntryKind::Leaf) => Err(Error::AlreadyMapped),
+    }
+}
+
+/// A page-table entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct Entry(u64);
+
+impl Entry {
+    pub const EMPTY: Entry = Entry(0);
+
+    /// The `V` bit inside a PTE.
+    pub const VALID: u64 = 1;
+    /// The `U` bit inside a PTE.
+    pub const USER: u64 = 1 << 4;
+    /// The `G` bit inside a PTE.
+    pub const GLOBAL: u64 = 1 << 5;
+    /// The `A` bit inside a PTE.
+    pub const ACCSES: u64 = 1 << 6;
+    /// The `D` bit inside a PTE.
+    pub const DIRTY: u64 = 1 << 7;
+    /// The `R`, `W` and `X` bits inside a PTE.
+    pub const PERM_MASK: u64 = 0b111 << 1;
+    /// All bits of a PTE, except for the physical page number.
+    pub const FLAGS_MASK: u64 = 0x3FF;
+
+    /// Set the raw value of this entry to the given value.
+    #[inline]
+    pub fn set(&mut self, x: u64) {
+        self.0 = x;
+    }
+
+    /// Get the raw value of this entry.
+    #[inline]
+    pub fn get(&self) -> u64 {
+        self.0
+    }
+
+    /// Get the kind of this entry.
+    ///
+    /// Returns `None` if this entry is invalid.
+    pub fn kind(&self) -> Option<EntryKind> {
+        match (self.valid(), self.branch()) {
+            (true, true) => {
+                let next = ((self.0 as usize >> 10) & 0x0FFF_FFFF_FFFF) << 12;
+                let next = PhysAddr::from(next);
+                Some(EntryKind::Branch(next))
+            }
+            (true, false) => Some(EntryKind::Leaf),
+            _ => None,
+        }
+    }
+
+    /// Check the `V` bit of this PTE.
+    #[inline]
+    pub fn valid(&self) -> bool {
+        self.0 & Entry::VALID != 0
+    }
+
+    /// Check if this PTE is a branch to the next level.
+    #[inline]
+    pub fn branch(&self) -> bool {
+        self.perm() == Perm::from(0u8) && self.valid()
+    }
+
+    /// Return the permissions for this PTE.
+    #[inline]
+    pub fn perm(&self) -> Perm {
+        let perm = (self.0 >> 1) & 0b111;
+        Perm::from(perm as u8)
+    }
+
+    /// Check if this PTE is accessible from U-Mode.
+    #[inline]
+    pub fn user(&self) -> bool {
+        self.0 & Entry::USER != 0
+    }
+
+    /// Check if this PTE is global mapping.
+    #[inline]
+    pub fn global(&self) -> bool {
+        self.0 & Entry::GLOBAL != 0
+    }
+
+    /// Check if the `A` bit of this PTE is high.
+    #[inline]
+    pub fn access(&self) -> bool {
+        self.0 & Entry::ACCSES != 0
+    }
+
+    /// Check if the `D` bit of this PTE is high.
+    #[inline]
+    pub fn dirty(&self) -> bool {
+        self.0 & Entry::DIRTY != 0
+    }
+
+    /// Return the physical page number for this entry.
+    #[inline]
+    pub fn ppn(&self) -> PhysAddr {
+        PhysAddr::from(((self.0 as usize >> 10) & 0x0FFF_FFFF_FFFF) << 12)
+    }
+}
+
+/// Represents the different kinds of page table entries.
+#[derive(Debug, Clone)]
+pub enum EntryKind {
+    /// This entry points to the entry in the next level.
+    Branch(PhysAddr),
+    /// This entry is a leaf and can directly be used to translate an address.
+    Leaf,
+}
+
+/// The end of a table walk.
+enum Slot {
+    /// The walk ended at an invalid entry at the given level.
+    Empty(usize),
+    /// The address is mapped by a leaf entry at the given level.
+    Leaf(usize),
+}
+
+/// Return the number of bytes that are covered by a single entry at the given level.
+fn level_size(level: usize) -> usize {
+    PageSize::Kilopage.size() << (9 * level)
+}
+
+fn align_down(addr: usize, align: usize) -> usize {
+    addr & !(align - 1)
+}
+
+/// Return the start of the entry at the given level, that follows the entry of `addr`.
+///
+/// Saturates at the end of the address space.
+fn next_page(addr: usize, level: usize) -> usize {
+    align_down(addr, level_size(level)).saturating_add(level_size(level))
+}
+
+/// Check that the range is page aligned, and doesn't overflow.
+///
+/// Returns the end of the range.
+fn check_range(start: usize, len: usize) -> Result<usize, Error> {
+    let size = PageSize::Kilopage;
+    if !size.is_aligned(start) || !size.is_aligned(len) {
+        return Err(Error::UnalignedAddress);
+    }
+
+    start.checked_add(len).ok_or(Error::InvalidRange)
+}
+
+/// Check that the permissions can be used for a leaf entry.
+///
+/// A leaf must be readable or executable, and writable pages must also be readable.
+fn check_perm(perm: Perm) -> Result<(), Error> {
+    if (!perm.read() && !perm.exec()) || (perm.write() && !perm.read()) {
+        return Err(Error::InvalidPerm);
+    }
+    Ok(())
+}
+
+/// Return the largest page size of the addressing mode, that can be used to map
+/// the given addresses, without exceeding `len` bytes.
+fn best_size(vaddr: usize, paddr: usize, len: usize) -> PageSize {
+    let mode = super::mode();
+
+    PageSize::ALL
+        .iter()
+        .rev()
+        .copied()
+        .filter(|&size| mode.supports(size))
+        .find(|&size| size.is_aligned(vaddr) && size.is_aligned(paddr) && len >= size.size())
+        .unwrap_or(PageSize::Kilopage)
+}
+
+/// Return the index into the table at the given level, that is used for the virtual address.
+fn vpn(vaddr: VirtAddr, level: usize) -> usize {
+    (usize::from(vaddr) >> (12 + 9 * level)) & 0x1FF
+}
+
+fn ppn_of_paddr(paddr: PhysAddr) -> usize {
+    (usize::from(paddr) >> 12) & 0x0FFF_FFFF_FFFF
+}
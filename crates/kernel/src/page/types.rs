@@ -1,5 +1,7 @@
+use super::{KERNEL_OFFSET, PHYSMAP_BASE, PHYSMAP_SIZE};
 use crate::unit;
 use core::{fmt, ops};
+use riscv::csr::satp;
 
 macro_rules! addr_type {
     ($(#[$attr:meta])* $pub:vis struct $name:ident;) => {
@@ -9,12 +11,12 @@ macro_rules! addr_type {
         $pub struct $name(usize);
 
         impl $name {
-            /// Interpret this physical address as a pointer to a `T`.
+            /// Interpret this address as a pointer to a `T`.
             pub fn as_ptr<T>(self) -> *mut T {
                 self.0 as *mut T
             }
 
-            /// Calculates the wrapping offset from this physical address.
+            /// Calculates the wrapping offset from this address.
             pub fn offset(self, off: usize) -> Self {
                 $name::from(self.0.wrapping_add(off))
             }
@@ -57,33 +59,115 @@ addr_type! {
     pub struct PhysAddr;
 }
 
+impl PhysAddr {
+    /// Return the address inside the physmap, that maps this physical address.
+    pub fn to_virt(self) -> VirtAddr {
+        VirtAddr::from(self.0 + PHYSMAP_BASE)
+    }
+}
+
+impl VirtAddr {
+    /// Translate an address inside the physmap, or inside the kernel image,
+    /// to its physical address.
+    ///
+    /// Returns `None` for every other address, which must be translated
+    /// using the page table.
+    pub fn to_phys(self) -> Option<PhysAddr> {
+        match self.0 {
+            addr if addr >= KERNEL_OFFSET => Some(PhysAddr::from(addr - KERNEL_OFFSET)),
+            addr if (PHYSMAP_BASE..PHYSMAP_BASE + PHYSMAP_SIZE).contains(&addr) => {
+                Some(PhysAddr::from(addr - PHYSMAP_BASE))
+            }
+            _ => None,
+        }
+    }
+}
+
 /// Represents the different kinds of pages that can be mapped.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PageSize {
     Kilopage,
     Megapage,
     Gigapage,
+    /// Only available in the Sv48 and Sv57 addressing modes.
+    Terapage,
 }
 
 impl PageSize {
-    pub fn is_aligned(self, addr: usize) -> bool {
-        let align = match self {
-            PageSize::Kilopage => 4 * unit::KIB,
-            PageSize::Megapage => 2 * unit::MIB,
-            PageSize::Gigapage => 1 * unit::GIB,
-        };
+    /// All page sizes, from the smallest to the largest.
+    pub const ALL: [PageSize; 4] = [
+        PageSize::Kilopage,
+        PageSize::Megapage,
+        PageSize::Gigapage,
+        PageSize::Terapage,
+    ];
 
-        addr % align == 0
+    pub fn is_aligned(self, addr: usize) -> bool {
+        addr % self.size() == 0
     }
 
     /// Return the number of bytes this page size covers.
-    ///
-    /// This will return the sizes for the Sv39 addressing mode.
     pub fn size(self) -> usize {
         match self {
             PageSize::Kilopage => 4 * unit::KIB,
             PageSize::Megapage => 2 * unit::MIB,
-            PageSize::Gigapage => 1 * unit::GIB,
+            PageSize::Gigapage => unit::GIB,
+            PageSize::Terapage => unit::TIB / 2,
+        }
+    }
+
+    /// Return the level of the page table, where a leaf entry maps a page of this size.
+    ///
+    /// Level `0` is the last level of the table walk.
+    pub fn level(self) -> usize {
+        self as usize
+    }
+
+    /// Return the page size that is mapped by a leaf entry at the given level.
+    pub fn from_level(level: usize) -> Option<PageSize> {
+        Self::ALL.get(level).copied()
+    }
+}
+
+/// The addressing modes, which differ in the number of levels of the page table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Sv39,
+    Sv48,
+    Sv57,
+}
+
+impl Mode {
+    /// Return the number of levels of the page table.
+    pub fn levels(self) -> usize {
+        match self {
+            Mode::Sv39 => 3,
+            Mode::Sv48 => 4,
+            Mode::Sv57 => 5,
+        }
+    }
+
+    /// Check if pages with the given size can be mapped in this mode.
+    pub fn supports(self, size: PageSize) -> bool {
+        size.level() < self.levels()
+    }
+
+    /// Return the mode for the `mmu-type` property of a `cpu` devicetree node.
+    pub fn from_mmu_type(mmu_type: &str) -> Option<Mode> {
+        match mmu_type {
+            "riscv,sv39" => Some(Mode::Sv39),
+            "riscv,sv48" => Some(Mode::Sv48),
+            "riscv,sv57" => Some(Mode::Sv57),
+            _ => None,
+        }
+    }
+
+    /// Return the value of the `MODE` field inside `satp` for this mode.
+    pub fn satp(self) -> satp::Mode {
+        match self {
+            Mode::Sv39 => satp::Mode::Sv39,
+            Mode::Sv48 => satp::Mode::Sv48,
+            Mode::Sv57 => satp::Mode::Sv57,
         }
     }
 }
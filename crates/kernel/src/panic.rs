@@ -1,9 +1,12 @@
-//! Custom Rust panic handler
+//! Custom Rust panic and allocation error handler
 
-use core::panic::PanicInfo;
+use crate::pmem;
+use core::{alloc::Layout, panic::PanicInfo};
 
 #[panic_handler]
 fn panic_handler(info: &PanicInfo<'_>) -> ! {
+    // interrupts may never be handled again, so the output must be polled
+    crate::console::switch_to_polling();
     let mut _guard = crate::console::lock();
 
     crate::error!(guard = _guard; "============");
@@ -23,5 +26,21 @@ fn panic_handler(info: &PanicInfo<'_>) -> ! {
         (None, None) => crate::error!(guard = _guard; "no information available."),
     }
 
+    crate::backtrace::print(&mut *_guard, crate::backtrace::frame_pointer());
+
+    drop(_guard);
     crate::arch::exit(1)
 }
+
+#[alloc_error_handler]
+fn alloc_error_handler(layout: Layout) -> ! {
+    crate::error!(
+        "Failed to allocate {} bytes with an alignment of {}",
+        layout.size(),
+        layout.align()
+    );
+    crate::error!("{}", pmem::alloc_stats());
+    crate::error!("{}", pmem::heap_stats());
+
+    panic!("out of memory")
+}
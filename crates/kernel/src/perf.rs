@@ -0,0 +1,176 @@
+//! Performance counters that are programmed using the SBI PMU extension.
+//!
+//! Counters are local to the hart that configured them, so a [`Counter`]
+//! must only be used on the hart that created it.
+
+#[cfg(feature = "bench")]
+pub mod bench;
+
+use crate::firmware::{self, Extension};
+use sbi::pmu::{self, Cache, CacheOp, CacheResult, CounterInfo, FirmwareEvent, HardwareEvent};
+
+displaydoc_lite::displaydoc! {
+    /// Errors that can happen while using performance counters.
+    #[derive(Debug, Clone)]
+    pub enum Error {
+        /// the counter can't be read from supervisor mode
+        Unreadable,
+        /// {_0}
+        Sbi(sbi::Error),
+    }
+}
+
+/// An event that can be counted by a [`Counter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// The number of elapsed cycles.
+    Cycles,
+    /// The number of retired instructions.
+    Instructions,
+    /// The number of data TLB misses.
+    DtlbMisses,
+    /// The number of instruction TLB misses.
+    ItlbMisses,
+    /// Any event that is caused by the SBI implementation.
+    Firmware(FirmwareEvent),
+}
+
+impl Event {
+    fn to_sbi(self) -> pmu::Event {
+        let tlb_miss = |cache| pmu::Event::Cache {
+            cache,
+            op: CacheOp::Read,
+            result: CacheResult::Miss,
+        };
+
+        match self {
+            Event::Cycles => pmu::Event::Hardware(HardwareEvent::CpuCycles),
+            Event::Instructions => pmu::Event::Hardware(HardwareEvent::Instructions),
+            Event::DtlbMisses => tlb_miss(Cache::Dtlb),
+            Event::ItlbMisses => tlb_miss(Cache::Itlb),
+            Event::Firmware(event) => pmu::Event::Firmware(event),
+        }
+    }
+}
+
+/// A hardware or firmware counter that is configured to count a single event.
+///
+/// The counter is released as soon as it's dropped.
+pub struct Counter {
+    idx: usize,
+    info: CounterInfo,
+}
+
+impl Counter {
+    /// Find a free counter that can count the given event and configure it.
+    ///
+    /// The counter is stopped and its value is cleared.
+    pub fn new(event: Event) -> Result<Self, Error> {
+        if !firmware::has(Extension::Pmu) {
+            return Err(Error::Sbi(sbi::Error::NotSupported));
+        }
+
+        let count = pmu::num_counters().map_err(Error::Sbi)?;
+        let mask = if count >= usize::BITS as usize {
+            usize::MAX
+        } else {
+            (1 << count) - 1
+        };
+
+        let idx = pmu::counter_config_matching(0, mask, pmu::CONFIG_CLEAR_VALUE, event.to_sbi())
+            .map_err(Error::Sbi)?;
+
+        // create the counter right away, so it's released if anything below fails
+        let mut counter = Self {
+            idx,
+            info: CounterInfo::new(0),
+        };
+
+        counter.info = pmu::counter_get_info(idx).map_err(Error::Sbi)?;
+        if !counter.info.is_firmware() && riscv::asm::rdcounter(counter.info.csr()).is_none() {
+            return Err(Error::Unreadable);
+        }
+
+        Ok(counter)
+    }
+
+    /// Return the index of this counter.
+    pub fn index(&self) -> usize {
+        self.idx
+    }
+
+    /// Reset the value of this counter to zero and start counting.
+    pub fn start(&self) -> Result<(), Error> {
+        pmu::counter_start(self.idx, 1, pmu::START_SET_INIT_VALUE, 0).map_err(Error::Sbi)
+    }
+
+    /// Stop counting, without changing the value of this counter.
+    pub fn stop(&self) -> Result<(), Error> {
+        pmu::counter_stop(self.idx, 1, 0).map_err(Error::Sbi)
+    }
+
+    /// Read the current value of this counter.
+    pub fn value(&self) -> Result<u64, Error> {
+        if self.info.is_firmware() {
+            return pmu::counter_fw_read(self.idx)
+                .map(|val| val as u64)
+                .map_err(Error::Sbi);
+        }
+
+        let val = riscv::asm::rdcounter(self.info.csr()).ok_or(Error::Unreadable)? as u64;
+        Ok(match self.info.width() {
+            64 => val,
+            width => val & ((1 << width) - 1),
+        })
+    }
+}
+
+impl Drop for Counter {
+    fn drop(&mut self) {
+        // the counter may already be stopped, which is fine
+        let _ = pmu::counter_stop(self.idx, 1, pmu::STOP_RESET);
+    }
+}
+
+/// Count the given events while executing `f`, and return the value of
+/// every counter in the same order as the events.
+///
+/// All counters are started right before, and stopped right after `f`,
+/// but they will still include the overhead of a few SBI calls.
+///
+/// `f` is not executed if any of the counters can't be started.
+pub fn measure<const N: usize>(events: [Event; N], f: impl FnOnce()) -> Result<[u64; N], Error> {
+    let counters = events.map(Counter::new);
+    if let Some(err) = counters.iter().find_map(|counter| counter.as_ref().err()) {
+        return Err(err.clone());
+    }
+    let counters = counters.map(Result::ok);
+
+    for (idx, counter) in counters.iter().enumerate() {
+        if let Some(Err(err)) = counter.as_ref().map(Counter::start) {
+            // don't leave the counters running that were already started
+            for counter in counters[..idx].iter().flatten() {
+                let _ = counter.stop();
+            }
+            return Err(err);
+        }
+    }
+
+    f();
+
+    // try to stop every counter, even if one of them failed
+    counters
+        .iter()
+        .flatten()
+        .map(Counter::stop)
+        .fold(Ok(()), Result::and)?;
+
+    let mut result = [0; N];
+    for (counter, out) in counters.iter().zip(result.iter_mut()) {
+        if let Some(counter) = counter {
+            *out = counter.value()?;
+        }
+    }
+
+    Ok(result)
+}
@@ -0,0 +1,87 @@
+//! Benchmarks of the page allocator and the page tables, that are run at boot
+//! if the `bench` feature is enabled.
+//!
+//! Every benchmark reports the number of cycles, retired instructions and
+//! data TLB misses. Benchmarks are skipped if there are no usable counters.
+
+use super::Event;
+use crate::{
+    page::{
+        table::{FreedTables, Table},
+        PageSize, Perm, PhysAddr, VirtAddr,
+    },
+    pmem,
+};
+use alloc::boxed::Box;
+
+/// The number of pages that are mapped by the page table benchmarks.
+const PAGE_COUNT: usize = 512;
+
+/// The events that are counted for every benchmark.
+const EVENTS: [Event; 3] = [Event::Cycles, Event::Instructions, Event::DtlbMisses];
+
+/// Run all benchmarks on the current hart, and log the results.
+pub fn run() {
+    info!("Running benchmarks...");
+
+    bench("allocate and free a page", || {
+        if let Ok(page) = pmem::alloc() {
+            unsafe { pmem::dealloc(page.as_non_null_ptr()) };
+        }
+    });
+
+    bench("allocate and free 16 pages", || {
+        if let Ok(pages) = pmem::alloc_pages(16) {
+            unsafe { pmem::dealloc_pages(pages.as_non_null_ptr(), 16) };
+        }
+    });
+
+    // the table is never used by any hart, so the tables can be freed right away
+    let mut table = Box::new(Table::new());
+    let mut freed = FreedTables::new();
+
+    // the physical address is not aligned to a megapage, so only kilopages are used
+    let vaddr = VirtAddr::from(0x4000_0000);
+    let paddr = PhysAddr::from(0x8000_1000);
+    let len = PAGE_COUNT * PageSize::Kilopage.size();
+
+    bench("map 512 pages", || {
+        if let Err(err) = table.map_range(vaddr, paddr, len, Perm::READ, &mut freed) {
+            warn!("Failed to map pages: {}", err);
+        }
+    });
+
+    bench("translate 512 pages", || {
+        for off in (0..len).step_by(PageSize::Kilopage.size()) {
+            let _ = table.translate(VirtAddr::from(usize::from(vaddr) + off));
+        }
+    });
+
+    bench("protect 512 pages", || {
+        if let Err(err) = table.protect_range(vaddr, len, Perm::READ | Perm::WRITE) {
+            warn!("Failed to protect pages: {}", err);
+        }
+    });
+
+    bench("unmap 512 pages", || {
+        if let Err(err) = table.unmap_range(vaddr, len, &mut freed) {
+            warn!("Failed to unmap pages: {}", err);
+        }
+    });
+
+    unsafe {
+        freed.free();
+        table.destroy();
+    }
+}
+
+/// Run a single benchmark and log the results.
+fn bench(name: &str, f: impl FnOnce()) {
+    match super::measure(EVENTS, f) {
+        Ok([cycles, instructions, misses]) => info!(
+            "{}: {} cycles, {} instructions, {} dTLB misses",
+            name, cycles, instructions, misses
+        ),
+        Err(err) => warn!("Skipped benchmark \"{}\": {}", name, err),
+    }
+}
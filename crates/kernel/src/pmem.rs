@@ -9,7 +9,10 @@ pub use linked_list::LinkedList;
 pub mod alloc;
 pub use self::alloc::Error as AllocError;
 
-use crate::unit;
+use crate::{
+    page::{self, PhysAddr, VirtAddr},
+    unit,
+};
 use core::{array, ptr::NonNull};
 use devicetree::DeviceTree;
 
@@ -28,7 +31,10 @@ displaydoc_lite::displaydoc! {
 
 /// Initialize the global memory allocator.
 ///
-/// Return the list of
+/// All memory is accessed through the physmap, so every pointer returned by
+/// the allocator is a virtual address inside the physmap.
+///
+/// Return the list of physical memory regions that are available for allocation.
 pub unsafe fn init(tree: &DeviceTree<'_>) -> Result<RangeSet, Error> {
     let mut memory = RangeSet::new();
 
@@ -57,8 +63,15 @@ pub unsafe fn init(tree: &DeviceTree<'_>) -> Result<RangeSet, Error> {
                 start, end
             );
 
-            let start = NonNull::new(start as *mut _).ok_or(Error::NullRegion)?;
-            let end = NonNull::new(end as *mut _).ok_or(Error::NullRegion)?;
+            if start == 0 {
+                return Err(Error::NullRegion);
+            }
+
+            // the allocator hands out pointers into the physmap
+            let start = PhysAddr::from(start).to_virt().as_ptr::<u8>();
+            let end = PhysAddr::from(end).to_virt().as_ptr::<u8>();
+            let start = NonNull::new(start).ok_or(Error::NullRegion)?;
+            let end = NonNull::new(end).ok_or(Error::NullRegion)?;
             alloc::allocator()
                 .add_region(start, end)
                 .map_err(Error::Alloc)?;
@@ -77,20 +90,27 @@ pub unsafe fn init(tree: &DeviceTree<'_>) -> Result<RangeSet, Error> {
 
 /// Get a list of memory ranges that must not be used for memory allocation,
 /// like the kernel itself and OpenSBI.
-fn get_blocked_ranges(tree: &DeviceTree<'_>) -> [Range; 3] {
+fn get_blocked_ranges(tree: &DeviceTree<'_>) -> [Range; 4] {
+    let phys = |addr: *const u8| {
+        let addr = VirtAddr::from(addr).to_phys();
+        usize::from(addr.expect("kernel and devicetree must be inside a linear mapping"))
+    };
+
     let (kernel_start, kernel_end) = riscv::symbols::kernel_range();
 
     // we align the end of the device tree to 4KiB to map them later
-    let fdt = tree.as_ptr() as usize;
+    let fdt = phys(tree.as_ptr());
     let fdt_end = alloc::align_up(fdt + tree.total_size() as usize, alloc::PAGE_SIZE) - 1;
 
     [
         // this range contains the OpenSBI firmware
         Range::new(0x8000_0000, 0x801F_FFFF),
         // the kernel itself
-        Range::new(kernel_start as _, kernel_end as usize - 1),
+        Range::new(phys(kernel_start), phys(kernel_end) - 1),
         // the actual device tree
         Range::new(fdt, fdt_end),
+        // memory that can't be accessed through the physmap
+        Range::new(page::PHYSMAP_SIZE, usize::MAX),
     ]
 }
 
@@ -99,6 +119,11 @@ pub fn alloc_stats() -> alloc::AllocStats {
     alloc::allocator().stats()
 }
 
+/// Return the statistics for the slab allocator of the kernel heap.
+pub fn heap_stats() -> alloc::AllocStats {
+    alloc::heap().stats()
+}
+
 /// Allocate a single page of physical memory.
 pub fn alloc() -> Result<NonNull<[u8]>, AllocError> {
     alloc::allocator().alloc()
@@ -3,8 +3,18 @@
 pub mod buddy;
 pub use buddy::BuddyAllocator;
 
-use crate::unit::{self, KIB};
-use core::{fmt, ptr::NonNull};
+pub mod slab;
+pub use slab::SlabAllocator;
+
+use crate::{
+    arch,
+    unit::{self, KIB},
+};
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    fmt,
+    ptr::{self, NonNull},
+};
 use displaydoc_lite::displaydoc;
 use riscv::sync::Mutex;
 
@@ -87,20 +97,28 @@ static PHYS_MEM_ALLOCATOR: GlobalAllocator = GlobalAllocator(Mutex::new(BuddyAll
 pub struct GlobalAllocator(Mutex<BuddyAllocator>);
 
 impl GlobalAllocator {
+    /// Run the given closure with the locked buddy allocator.
+    ///
+    /// The heap may allocate pages inside interrupt handlers, so the
+    /// lock must always be taken with interrupts disabled.
+    fn with<R>(&self, f: impl FnOnce(&mut BuddyAllocator) -> R) -> R {
+        arch::without_interrupts(|| f(&mut self.0.lock()))
+    }
+
     /// Adds a single region of memory to this allocator and makes it available for allocation.
     pub unsafe fn add_region(&self, start: NonNull<u8>, end: NonNull<u8>) -> Result<usize> {
-        self.0.lock().add_region(start, end)
+        self.with(|alloc| alloc.add_region(start, end))
     }
 
     /// Allocatge a single page of physmem.
     pub fn alloc(&self) -> Result<NonNull<[u8]>, Error> {
         // order 0 is exactly the page size
-        self.0.lock().allocate(0)
+        self.with(|alloc| alloc.allocate(0))
     }
 
     /// Deallocate the given page.
     pub unsafe fn dealloc(&self, ptr: NonNull<u8>) {
-        match self.0.lock().deallocate(ptr, 0) {
+        match self.with(|alloc| alloc.deallocate(ptr, 0)) {
             Ok(()) => {}
             Err(err) => warn!("Failed to deallocate page: {}", err),
         }
@@ -114,7 +132,7 @@ impl GlobalAllocator {
         }
         let total = count * PAGE_SIZE;
 
-        match self.0.lock().deallocate(ptr, buddy::order_for_size(total)) {
+        match self.with(|alloc| alloc.deallocate(ptr, buddy::order_for_size(total))) {
             Ok(()) => {}
             Err(err) => warn!("Failed to deallocate page: {}", err),
         }
@@ -130,18 +148,86 @@ impl GlobalAllocator {
         // we calculate the required size to fit `count` pages,
         // and then get the order for this size
         let total = count * PAGE_SIZE;
-        self.0.lock().allocate(buddy::order_for_size(total))
+        self.with(|alloc| alloc.allocate(buddy::order_for_size(total)))
     }
 
     /// Return the statistics for this allocator.
     pub fn stats(&self) -> AllocStats {
-        self.0.lock().stats()
+        self.with(|alloc| alloc.stats())
     }
 }
 
 unsafe impl Send for GlobalAllocator {}
 unsafe impl Sync for GlobalAllocator {}
 
+#[global_allocator]
+static HEAP: Heap = Heap(Mutex::new(SlabAllocator::new()));
+
+/// The allocator that is used for all allocations of the `alloc` crate.
+///
+/// Small objects are allocated by the slab allocator, while
+/// larger ones directly use pages of the physical memory allocator.
+///
+/// Allocations with an alignment larger than [`PAGE_SIZE`] are not reliably
+/// supported. The buddy allocator only aligns its blocks to the page size,
+/// so these allocations fail whenever the block is not aligned by chance.
+pub struct Heap(Mutex<SlabAllocator>);
+
+impl Heap {
+    /// Return the statistics for the slab allocator of this heap.
+    pub fn stats(&self) -> AllocStats {
+        arch::without_interrupts(|| self.0.lock().stats())
+    }
+}
+
+unsafe impl GlobalAlloc for Heap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if let Some(class) = slab::class_for_layout(layout) {
+            return arch::without_interrupts(|| self.0.lock().allocate(class))
+                .map_or(ptr::null_mut(), NonNull::as_ptr);
+        }
+
+        let count = pages_for_layout(layout);
+        let ptr = match allocator().alloc_pages(count) {
+            Ok(ptr) => ptr.as_mut_ptr(),
+            Err(_) => return ptr::null_mut(),
+        };
+
+        // the buddy allocator only guarantees alignment to the page size
+        if ptr as usize % layout.align() != 0 {
+            allocator().dealloc_pages(NonNull::new_unchecked(ptr), count);
+            return ptr::null_mut();
+        }
+
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let ptr = match NonNull::new(ptr) {
+            Some(ptr) => ptr,
+            None => return,
+        };
+
+        match slab::class_for_layout(layout) {
+            Some(class) => arch::without_interrupts(|| self.0.lock().deallocate(ptr, class)),
+            None => allocator().dealloc_pages(ptr, pages_for_layout(layout)),
+        }
+    }
+}
+
+unsafe impl Send for Heap {}
+unsafe impl Sync for Heap {}
+
+/// Calculate the number of pages that are required to fit the given layout.
+fn pages_for_layout(layout: Layout) -> usize {
+    align_up(layout.size().max(layout.align()), PAGE_SIZE) / PAGE_SIZE
+}
+
+/// Return a reference to the heap that is used by the `alloc` crate.
+pub(super) fn heap() -> &'static Heap {
+    &HEAP
+}
+
 /// Return a reference to the global allocator for physical memory.
 pub(super) fn allocator() -> &'static GlobalAllocator {
     &PHYS_MEM_ALLOCATOR
@@ -1,5 +1,5 @@
 //! Implementation of a Buddy Allocator that is responsible for allocating
-//! the physical memory that will then be used by either the slab allocator
+//! the physical memory that will then be used by either the [slab allocator](super::slab)
 //! to allocate objects, or directly by the kernel.
 
 use super::{align_up, AllocStats, Error, Result};
@@ -0,0 +1,118 @@
+//! Implementation of a Slab Allocator, that splits the pages of the buddy
+//! allocator into objects of a fixed size.
+//!
+//! Every size class is a power of two, and has a list of free objects. If the list
+//! is empty, a new page is requested from the buddy allocator and split into objects.
+//! Objects are always aligned to their size, because the pages are aligned to
+//! the page size.
+//!
+//! Pages are never given back to the buddy allocator, but the objects are reused
+//! by later allocations of the same size class.
+
+use super::{AllocStats, Error, Result, PAGE_SIZE};
+use crate::pmem::LinkedList;
+use core::{alloc::Layout, ptr::NonNull};
+
+/// The size of the smallest size class.
+pub const MIN_SIZE: usize = 16;
+
+/// The size of the largest size class.
+///
+/// Larger allocations must be made directly using the buddy allocator.
+pub const MAX_SIZE: usize = 2048;
+
+/// The number of different size classes.
+pub const CLASS_COUNT: usize = (MAX_SIZE / MIN_SIZE).trailing_zeros() as usize + 1;
+
+/// Return the index of the size class that fits objects with the given layout,
+/// or `None` if the layout is too large for the slab allocator.
+pub fn class_for_layout(layout: Layout) -> Option<usize> {
+    let size = layout.size().max(layout.align()).max(MIN_SIZE);
+    let size = size.checked_next_power_of_two()?;
+
+    if size > MAX_SIZE {
+        return None;
+    }
+
+    Some((size / MIN_SIZE).trailing_zeros() as usize)
+}
+
+/// Calculates the object size for the given size class.
+pub fn size_for_class(class: usize) -> usize {
+    MIN_SIZE << class
+}
+
+/// The central structure that is responsible for allocating
+/// small objects using size classes.
+pub struct SlabAllocator {
+    classes: [LinkedList; CLASS_COUNT],
+    stats: AllocStats,
+}
+
+impl SlabAllocator {
+    /// Create an empty slab allocator, that has no pages yet.
+    pub const fn new() -> Self {
+        Self {
+            classes: [LinkedList::EMPTY; CLASS_COUNT],
+            stats: AllocStats::with_name("Slab Allocator"),
+        }
+    }
+
+    /// Allocate a single object of the given size class.
+    ///
+    /// If there's no free object left, a new page is requested from the buddy allocator.
+    pub fn allocate(&mut self, class: usize) -> Result<NonNull<u8>> {
+        let size = size_for_class(class);
+
+        if self.classes[class].is_empty() {
+            self.refill(class)?;
+        }
+
+        let obj = self.classes[class].pop().ok_or(Error::NoMemoryAvailable)?;
+
+        // update statistics
+        self.stats.free = self.stats.free.saturating_sub(size);
+        self.stats.allocated = self.stats.allocated.saturating_add(size);
+
+        Ok(obj.cast())
+    }
+
+    /// Deallocates an object, that was allocated using the given size class.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must be allocated by `self` using the [`Self::allocate`] method
+    /// with the same size class as given here.
+    pub unsafe fn deallocate(&mut self, obj: NonNull<u8>, class: usize) {
+        let size = size_for_class(class);
+        self.classes[class].push(obj.cast());
+
+        // update statistics
+        self.stats.free = self.stats.free.saturating_add(size);
+        self.stats.allocated = self.stats.allocated.saturating_sub(size);
+    }
+
+    /// Return a copy of the statistics for this allocator.
+    pub fn stats(&self) -> AllocStats {
+        self.stats.clone()
+    }
+
+    /// Request a new page from the buddy allocator, and split it into objects
+    /// of the given size class.
+    fn refill(&mut self, class: usize) -> Result<()> {
+        let size = size_for_class(class);
+        let page = super::allocator().alloc()?;
+        let start = page.as_mut_ptr();
+
+        // push the objects in reverse, so they are allocated in ascending order
+        for off in (0..PAGE_SIZE).step_by(size).rev() {
+            let obj = NonNull::new(unsafe { start.add(off) }).ok_or(Error::NullPointer)?;
+            unsafe { self.classes[class].push(obj.cast()) };
+        }
+
+        // update statistics
+        self.stats.total += PAGE_SIZE;
+        self.stats.free += PAGE_SIZE;
+        Ok(())
+    }
+}
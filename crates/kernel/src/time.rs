@@ -0,0 +1,164 @@
+//! Monotonic clock based on the `time` CSR.
+//!
+//! The frequency of the `time` CSR is read from the `timebase-frequency`
+//! property of the `/cpus` devicetree node at boot.
+
+use core::{
+    fmt, ops,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+use devicetree::DeviceTree;
+
+/// The number of nanoseconds inside one second.
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+/// The frequency of the `time` CSR in Hz.
+///
+/// This is `0` until [`init`] was called.
+static FREQUENCY: AtomicU64 = AtomicU64::new(0);
+
+displaydoc_lite::displaydoc! {
+    /// Errors that can happen while initializing the clock.
+    #[derive(Debug)]
+    pub enum Error {
+        /// the devicetree doesn't contain a `/cpus` node
+        NoCpusNode,
+        /// the `/cpus` node doesn't contain a `timebase-frequency` property
+        NoTimebaseFrequency,
+    }
+}
+
+/// Initialize the clock by reading the timebase frequency from the devicetree.
+pub fn init(tree: &DeviceTree<'_>) -> Result<(), Error> {
+    let cpus = tree.find_node("/cpus").ok_or(Error::NoCpusNode)?;
+
+    // the property is usually inside the `/cpus` node, but some trees put
+    // it into every `cpu` node instead.
+    let freq = cpus
+        .prop("timebase-frequency")
+        .or_else(|| {
+            cpus.children()
+                .find_map(|cpu| cpu.prop("timebase-frequency"))
+        })
+        .and_then(|prop| prop.as_u32())
+        .filter(|&freq| freq != 0)
+        .ok_or(Error::NoTimebaseFrequency)?;
+
+    FREQUENCY.store(freq as u64, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Return the frequency of the `time` CSR in Hz.
+pub fn frequency() -> u64 {
+    FREQUENCY.load(Ordering::Relaxed)
+}
+
+/// Convert the given duration into the number of ticks of the `time` CSR.
+pub fn duration_to_ticks(dur: Duration) -> u64 {
+    (dur.as_nanos() * frequency() as u128 / NANOS_PER_SEC) as u64
+}
+
+/// Convert the given number of ticks of the `time` CSR into a duration.
+///
+/// Returns a zero duration if the clock is not initialized yet.
+pub fn ticks_to_duration(ticks: u64) -> Duration {
+    match frequency() {
+        0 => Duration::from_secs(0),
+        freq => {
+            let nanos = ticks as u128 * NANOS_PER_SEC / freq as u128;
+            Duration::from_nanos(nanos as u64)
+        }
+    }
+}
+
+/// A point in time, measured using the monotonic `time` CSR.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant(u64);
+
+impl Instant {
+    /// Return an instant that represents the current time.
+    pub fn now() -> Self {
+        Self(riscv::asm::rdtime() as u64)
+    }
+
+    /// Create an instant from the raw value of the `time` CSR.
+    pub fn from_ticks(ticks: u64) -> Self {
+        Self(ticks)
+    }
+
+    /// Return the raw value of the `time` CSR at this instant.
+    pub fn ticks(self) -> u64 {
+        self.0
+    }
+
+    /// Return the amount of time elapsed from another instant to this one,
+    /// or a zero duration if `earlier` is later than this instant.
+    pub fn duration_since(self, earlier: Instant) -> Duration {
+        ticks_to_duration(self.0.saturating_sub(earlier.0))
+    }
+
+    /// Return the amount of time elapsed since this instant was created.
+    pub fn elapsed(self) -> Duration {
+        Instant::now().duration_since(self)
+    }
+
+    /// Return the time since boot, at this instant.
+    pub fn since_boot(self) -> Duration {
+        ticks_to_duration(self.0)
+    }
+
+    /// Add the duration to this instant, returning `None` if the result overflowed.
+    pub fn checked_add(self, dur: Duration) -> Option<Instant> {
+        self.0.checked_add(duration_to_ticks(dur)).map(Instant)
+    }
+
+    /// Subtract the duration from this instant, returning `None` if the result underflowed.
+    pub fn checked_sub(self, dur: Duration) -> Option<Instant> {
+        self.0.checked_sub(duration_to_ticks(dur)).map(Instant)
+    }
+}
+
+impl ops::Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, dur: Duration) -> Instant {
+        self.checked_add(dur)
+            .expect("overflow when adding duration to instant")
+    }
+}
+
+impl ops::AddAssign<Duration> for Instant {
+    fn add_assign(&mut self, dur: Duration) {
+        *self = *self + dur;
+    }
+}
+
+impl ops::Sub<Duration> for Instant {
+    type Output = Instant;
+
+    fn sub(self, dur: Duration) -> Instant {
+        self.checked_sub(dur)
+            .expect("overflow when subtracting duration from instant")
+    }
+}
+
+impl ops::SubAssign<Duration> for Instant {
+    fn sub_assign(&mut self, dur: Duration) {
+        *self = *self - dur;
+    }
+}
+
+impl ops::Sub<Instant> for Instant {
+    type Output = Duration;
+
+    fn sub(self, other: Instant) -> Duration {
+        self.duration_since(other)
+    }
+}
+
+impl fmt::Debug for Instant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Instant").field(&self.since_boot()).finish()
+    }
+}
@@ -0,0 +1,173 @@
+//! Kernel timers that are driven by the SBI timer extension.
+//!
+//! Every timer has a callback which is executed inside the trap handler,
+//! with interrupts disabled, as soon as the deadline of the timer passed.
+
+use crate::{
+    arch,
+    firmware::{self, Extension},
+    time::{self, Instant},
+};
+use core::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+use riscv::{csr::sie, sync::Mutex};
+
+/// The maximum number of timers that can be active at the same time.
+pub const MAX_TIMERS: usize = 32;
+
+/// The list of all active timers.
+static TIMERS: Mutex<[Option<Timer>; MAX_TIMERS]> = Mutex::new([None; MAX_TIMERS]);
+
+/// Counter that is used to generate unique timer ids.
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+displaydoc_lite::displaydoc! {
+    /// Errors that are related to kernel timers.
+    #[derive(Debug)]
+    pub enum Error {
+        /// the maximum number of active timers was reached
+        TooManyTimers,
+        /// the period of a periodic timer must not be zero
+        ZeroPeriod,
+    }
+}
+
+/// A unique identifier of a timer, that can be used to cancel it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimerId(u64);
+
+#[derive(Clone, Copy)]
+struct Timer {
+    id: TimerId,
+    /// The point in time at which this timer fires.
+    deadline: Instant,
+    /// The time between two invocations of a periodic timer.
+    period: Option<Duration>,
+    callback: fn(),
+}
+
+/// Initialize the timer subsystem by enabling timer interrupts on this hart.
+///
+/// The clock must be [initialized](time::init) before any timer is created.
+pub fn init() {
+    // make sure there's no pending timer interrupt
+    let _ = set_timer(u64::MAX);
+    sie::set_stimer();
+
+    info!("{} kernel timers", "Initialized".green());
+}
+
+/// Create a timer that will execute `callback` once after `delay`.
+pub fn oneshot(delay: Duration, callback: fn()) -> Result<TimerId, Error> {
+    add(delay, None, callback)
+}
+
+/// Create a timer that will execute `callback` every `period`, until it's cancelled.
+pub fn periodic(period: Duration, callback: fn()) -> Result<TimerId, Error> {
+    // a period that is shorter than one tick would fire on every interrupt
+    if time::duration_to_ticks(period) == 0 {
+        return Err(Error::ZeroPeriod);
+    }
+
+    add(period, Some(period), callback)
+}
+
+/// Cancel the timer with the given id.
+///
+/// Returns `true` if the timer was still active.
+pub fn cancel(id: TimerId) -> bool {
+    arch::without_interrupts(|| {
+        let mut timers = TIMERS.lock();
+        let idx = timers
+            .iter()
+            .position(|timer| matches!(timer, Some(t) if t.id == id));
+
+        match idx {
+            Some(idx) => {
+                timers[idx] = None;
+                program(&*timers);
+                true
+            }
+            None => false,
+        }
+    })
+}
+
+fn add(delay: Duration, period: Option<Duration>, callback: fn()) -> Result<TimerId, Error> {
+    let id = TimerId(NEXT_ID.fetch_add(1, Ordering::Relaxed));
+    let timer = Timer {
+        id,
+        deadline: Instant::now() + delay,
+        period,
+        callback,
+    };
+
+    arch::without_interrupts(|| {
+        let mut timers = TIMERS.lock();
+        let slot = timers
+            .iter_mut()
+            .find(|timer| timer.is_none())
+            .ok_or(Error::TooManyTimers)?;
+
+        *slot = Some(timer);
+        program(&*timers);
+        Ok(id)
+    })
+}
+
+/// Handle a supervisor timer interrupt by running all expired timers.
+///
+/// This function is called from the trap handler.
+pub fn handle_interrupt() {
+    // collect all expired timers first, so the callbacks
+    // can create or cancel timers without deadlocking
+    let mut expired = [None; MAX_TIMERS];
+    {
+        let now = Instant::now();
+        let mut timers = TIMERS.lock();
+
+        for (slot, out) in timers.iter_mut().zip(expired.iter_mut()) {
+            match slot {
+                Some(timer) if timer.deadline <= now => {
+                    *out = Some(timer.callback);
+
+                    match timer.period {
+                        Some(period) => timer.deadline = now + period,
+                        None => *slot = None,
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        program(&*timers);
+    }
+
+    expired.iter().flatten().for_each(|callback| callback());
+}
+
+/// Program the SBI timer for the earliest deadline inside the given list.
+fn program(timers: &[Option<Timer>]) {
+    let next = timers
+        .iter()
+        .flatten()
+        .map(|timer| timer.deadline.ticks())
+        .min()
+        // setting the timer to the maximum value also clears the pending interrupt
+        .unwrap_or(u64::MAX);
+
+    if let Err(err) = set_timer(next) {
+        warn!("Failed to program the timer: {:?}", err);
+    }
+}
+
+/// Program the SBI timer, using the legacy extension if the timer extension doesn't exist.
+fn set_timer(stime: u64) -> sbi::SbiResult<()> {
+    if firmware::has(Extension::Timer) {
+        sbi::timer::set_timer(stime)
+    } else {
+        sbi::legacy::set_timer(stime)
+    }
+}
@@ -1,6 +1,284 @@
 //! Implementation of the trap handler.
 
+use crate::{
+    hart,
+    page::{self, VirtAddr},
+};
+use core::fmt;
+use riscv::{
+    csr::{satp, scause, sepc, sstatus, stval, stvec},
+    trap::{Trap, TrapFrame, XREG_NAMES},
+};
+
+/// The number of bytes that are reserved on the stack for a [`TrapFrame`].
+///
+/// This is the size of the trap frame rounded up to 16 bytes, because
+/// the stack pointer must always be 16 byte aligned.
+const FRAME_SIZE: usize = 256;
+
+/// Install the trap vector for the current hart by writing
+/// its address into the `stvec` register.
+pub fn init() {
+    stvec::write(trap_vector as usize, stvec::Mode::Direct);
+}
+
+/// The rust entrypoint for every trap.
+#[no_mangle]
+extern "C" fn trap_handler(frame: &mut TrapFrame) {
+    let scause = scause::read();
+    let epc = sepc::read();
+
+    let epc = match scause.cause() {
+        Trap::Reserved => panic!("Invalid trap cause {:#x} at {:#x}", scause.bits(), epc),
+        trap if scause.is_interrupt() => match hart::try_current() {
+            Some(local) => local.interrupt(|| handle_interrupt(trap, frame, epc)),
+            None => handle_interrupt(trap, frame, epc),
+        },
+        trap => handle_exception(trap, frame, stval::read(), epc),
+    };
+
+    // the handler returns the address where execution continues
+    sepc::write(epc);
+}
+
+/// Dispatch an interrupt to its subsystem.
+///
+/// Logging is fine here, because the console lock is always taken with interrupts disabled.
+fn handle_interrupt(trap: Trap, _frame: &mut TrapFrame, epc: usize) -> usize {
+    match trap {
+        Trap::SupervisorSoftwareInterrupt => crate::ipi::handle_interrupt(),
+        Trap::SupervisorTimerInterrupt => crate::timer::handle_interrupt(),
+        Trap::SupervisorExternalInterrupt => crate::irq::handle_interrupt(),
+        trap => warn!("Unhandled interrupt {:?} at {:#x}", trap, epc),
+    }
+
+    epc
+}
+
+fn handle_exception(trap: Trap, frame: &mut TrapFrame, tval: usize, epc: usize) -> usize {
+    match trap {
+        Trap::Breakpoint => {
+            debug!("Hit breakpoint at {:#x}", epc);
+            epc + instruction_len(epc)
+        }
+        trap => fatal_exception(trap, frame, tval, epc),
+    }
+}
+
+/// Print a report containing all information about an exception,
+/// that can't be handled, and exit the kernel.
+fn fatal_exception(trap: Trap, frame: &TrapFrame, tval: usize, epc: usize) -> ! {
+    let sstatus = sstatus::read().bits();
+
+    crate::console::switch_to_polling();
+    let mut _guard = crate::console::lock();
+
+    error!(guard = _guard; "================");
+    error!(guard = _guard; "FATAL EXCEPTION");
+    error!(guard = _guard; "================");
+    error!(guard = _guard; "{} at {:#018x}", format_args!("{:?}", trap).red(), epc);
+    error!(guard = _guard; "sepc: {:#018x} stval: {:#018x} sstatus: {:#018x}", epc, tval, sstatus);
+
+    // print all registers, four in each line
+    for (names, regs) in XREG_NAMES.chunks(4).zip(frame.xregs.chunks(4)) {
+        error!(guard = _guard; "{}", Registers { names, regs });
+    }
+
+    // try to tell where the faulting address points to
+    match translate(tval.into()) {
+        Some((paddr, size)) => error!(
+            guard = _guard;
+            "stval {:#x} is mapped to {:#x} using a {:?}",
+            tval, usize::from(paddr), size
+        ),
+        None => error!(guard = _guard; "stval {:#x} is not mapped", tval),
+    }
+
+    // the backtrace starts at the faulting instruction, followed
+    // by the frame-pointer chain of the interrupted code
+    error!(
+        guard = _guard;
+        "Faulting instruction: {:#018x} {}",
+        epc, crate::ksyms::resolve(epc)
+    );
+    crate::backtrace::print(&mut *_guard, frame.xregs[7]);
+
+    drop(_guard);
+    crate::arch::exit(1)
+}
+
+/// Translate the given address using the current page table, if paging is enabled.
+fn translate(vaddr: VirtAddr) -> Option<(page::PhysAddr, page::PageSize)> {
+    if matches!(satp::read().mode, satp::Mode::Bare) {
+        return None;
+    }
+
+    unsafe { page::root().translate(vaddr) }
+}
+
+/// Helper to format a line of registers together with their ABI names.
+struct Registers<'a> {
+    names: &'a [&'static str],
+    regs: &'a [usize],
+}
+
+impl fmt::Display for Registers<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, reg) in self.names.iter().zip(self.regs) {
+            write!(f, "{:>3}: {:#018x}  ", name, reg)?;
+        }
+        Ok(())
+    }
+}
+
+/// Return the length in bytes of the instruction at `epc`,
+/// which is either `2` for compressed or `4` for normal instructions.
+fn instruction_len(epc: usize) -> usize {
+    let inst = unsafe { core::ptr::read_volatile(epc as *const u16) };
+    if inst & 0b11 == 0b11 {
+        4
+    } else {
+        2
+    }
+}
+
+/// The trap vector that is written into `stvec`.
+///
+/// Saves all registers into a [`TrapFrame`], calls [`trap_handler`] and restores
+/// the registers before returning using `sret`.
+///
+/// The frame is pushed onto the current stack, if it's inside the kernel stack of this hart.
+/// Otherwise, like after a stack overflow, the vector switches to the top of the kernel stack,
+/// which is found through the [`HartLocal`](crate::hart::HartLocal) structure in `sscratch`.
+/// The trapped code can't continue in that case, but the trap can still be reported.
 #[naked]
-unsafe extern "C" fn trap_handler() {
-    asm!("", options(noreturn))
+#[link_section = ".text.trap"]
+unsafe extern "C" fn trap_vector() -> ! {
+    asm!(
+        // ---------------------------------
+        // Allocate the trap frame, on the kernel
+        // stack if `sp` is outside of it
+        // ---------------------------------
+        "csrrw t0, sscratch, t0",
+        "beqz t0, 3f",
+        "sd t1, {scratch}(t0)",
+        // keep the current stack, if the frame fits between its bottom and top
+        "ld t1, {bottom}(t0)",
+        "addi t1, t1, {size}",
+        "bltu sp, t1, 1f",
+        "ld t1, {top}(t0)",
+        "bgtu sp, t1, 1f",
+        "addi sp, sp, -{size}",
+        "addi t1, sp, {size}",
+        "sd t1, 1*8(sp)",
+        "j 2f",
+        "1:",
+        // switch to the top of the kernel stack
+        "mv t1, sp",
+        "ld sp, {top}(t0)",
+        "addi sp, sp, -{size}",
+        "sd t1, 1*8(sp)",
+        "2:",
+        "ld t1, {scratch}(t0)",
+        "csrrw t0, sscratch, t0",
+        "j 4f",
+        // there's no `HartLocal` structure yet, so the current stack is used
+        "3:",
+        "csrrw t0, sscratch, t0",
+        "addi sp, sp, -{size}",
+        "sd t0, 4*8(sp)",
+        "addi t0, sp, {size}",
+        "sd t0, 1*8(sp)",
+        "ld t0, 4*8(sp)",
+        "4:",
+        // ---------------------------------
+        // Save all registers, the stack
+        // pointer was already saved above
+        // ---------------------------------
+        "sd x1, 0*8(sp)",
+        "sd x3, 2*8(sp)",
+        "sd x4, 3*8(sp)",
+        "sd x5, 4*8(sp)",
+        "sd x6, 5*8(sp)",
+        "sd x7, 6*8(sp)",
+        "sd x8, 7*8(sp)",
+        "sd x9, 8*8(sp)",
+        "sd x10, 9*8(sp)",
+        "sd x11, 10*8(sp)",
+        "sd x12, 11*8(sp)",
+        "sd x13, 12*8(sp)",
+        "sd x14, 13*8(sp)",
+        "sd x15, 14*8(sp)",
+        "sd x16, 15*8(sp)",
+        "sd x17, 16*8(sp)",
+        "sd x18, 17*8(sp)",
+        "sd x19, 18*8(sp)",
+        "sd x20, 19*8(sp)",
+        "sd x21, 20*8(sp)",
+        "sd x22, 21*8(sp)",
+        "sd x23, 22*8(sp)",
+        "sd x24, 23*8(sp)",
+        "sd x25, 24*8(sp)",
+        "sd x26, 25*8(sp)",
+        "sd x27, 26*8(sp)",
+        "sd x28, 27*8(sp)",
+        "sd x29, 28*8(sp)",
+        "sd x30, 29*8(sp)",
+        "sd x31, 30*8(sp)",
+        // ---------------------------------
+        // Restore the thread pointer from the
+        // `HartLocal` structure, if there's one
+        // ---------------------------------
+        "csrr t0, sscratch",
+        "beqz t0, 5f",
+        "ld tp, {tls}(t0)",
+        "5:",
+        // ---------------------------------
+        // Call the rust trap handler
+        // ---------------------------------
+        "mv a0, sp",
+        "call trap_handler",
+        // ---------------------------------
+        // Restore all registers
+        // ---------------------------------
+        "ld x1, 0*8(sp)",
+        "ld x3, 2*8(sp)",
+        "ld x4, 3*8(sp)",
+        "ld x5, 4*8(sp)",
+        "ld x6, 5*8(sp)",
+        "ld x7, 6*8(sp)",
+        "ld x8, 7*8(sp)",
+        "ld x9, 8*8(sp)",
+        "ld x10, 9*8(sp)",
+        "ld x11, 10*8(sp)",
+        "ld x12, 11*8(sp)",
+        "ld x13, 12*8(sp)",
+        "ld x14, 13*8(sp)",
+        "ld x15, 14*8(sp)",
+        "ld x16, 15*8(sp)",
+        "ld x17, 16*8(sp)",
+        "ld x18, 17*8(sp)",
+        "ld x19, 18*8(sp)",
+        "ld x20, 19*8(sp)",
+        "ld x21, 20*8(sp)",
+        "ld x22, 21*8(sp)",
+        "ld x23, 22*8(sp)",
+        "ld x24, 23*8(sp)",
+        "ld x25, 24*8(sp)",
+        "ld x26, 25*8(sp)",
+        "ld x27, 26*8(sp)",
+        "ld x28, 27*8(sp)",
+        "ld x29, 28*8(sp)",
+        "ld x30, 29*8(sp)",
+        "ld x31, 30*8(sp)",
+        // restoring `sp` must be the last operation
+        "ld x2, 1*8(sp)",
+        "sret",
+        size = const FRAME_SIZE,
+        tls = const hart::TLS_FIELD,
+        top = const hart::STACK_TOP_FIELD,
+        bottom = const hart::STACK_BOTTOM_FIELD,
+        scratch = const hart::SCRATCH_FIELD,
+        options(noreturn)
+    )
 }
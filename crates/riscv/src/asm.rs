@@ -30,6 +30,45 @@ pub fn rdtime() -> usize {
     x
 }
 
+/// Read the unprivileged counter CSR with the given number.
+///
+/// Valid numbers are `0xC00` (`cycle`) to `0xC1F` (`hpmcounter31`),
+/// for any other number `None` is returned.
+pub fn rdcounter(csr: u16) -> Option<usize> {
+    macro_rules! read {
+        ($($num:literal => $name:literal),* $(,)?) => {
+            match csr {
+                $($num => {
+                    let x: usize;
+                    unsafe { asm!(concat!("csrr {}, ", $name), out(reg) x) };
+                    Some(x)
+                })*
+                _ => None,
+            }
+        };
+    }
+
+    read! {
+        0xC00 => "cycle", 0xC01 => "time", 0xC02 => "instret",
+        0xC03 => "hpmcounter3", 0xC04 => "hpmcounter4", 0xC05 => "hpmcounter5",
+        0xC06 => "hpmcounter6", 0xC07 => "hpmcounter7", 0xC08 => "hpmcounter8",
+        0xC09 => "hpmcounter9", 0xC0A => "hpmcounter10", 0xC0B => "hpmcounter11",
+        0xC0C => "hpmcounter12", 0xC0D => "hpmcounter13", 0xC0E => "hpmcounter14",
+        0xC0F => "hpmcounter15", 0xC10 => "hpmcounter16", 0xC11 => "hpmcounter17",
+        0xC12 => "hpmcounter18", 0xC13 => "hpmcounter19", 0xC14 => "hpmcounter20",
+        0xC15 => "hpmcounter21", 0xC16 => "hpmcounter22", 0xC17 => "hpmcounter23",
+        0xC18 => "hpmcounter24", 0xC19 => "hpmcounter25", 0xC1A => "hpmcounter26",
+        0xC1B => "hpmcounter27", 0xC1C => "hpmcounter28", 0xC1D => "hpmcounter29",
+        0xC1E => "hpmcounter30", 0xC1F => "hpmcounter31",
+    }
+}
+
+/// Wrapper around the `fence.i` instruction.
+#[inline]
+pub fn fence_i() {
+    unsafe { asm!("fence.i") }
+}
+
 /// Execute a `sfence.vma` instruction for the given address and asid.
 #[inline]
 pub fn sfence(addr: impl Into<Option<usize>>, asid: impl Into<Option<u16>>) {
@@ -3,9 +3,20 @@
 #[macro_use]
 mod macros;
 
+pub mod mie;
+pub mod mip;
 pub mod satp;
+pub mod scause;
+pub mod sie;
+pub mod sip;
+pub mod sstatus;
+pub mod stvec;
 
 csr_mod!(r, mvendorid, 0xF11);
 csr_mod!(r, marchid, 0xF12);
 csr_mod!(r, mimpid, 0xF13);
 csr_mod!(r, mhartid, 0xF14);
+
+csr_mod!(rw, sscratch, 0x140);
+csr_mod!(rw, sepc, 0x141);
+csr_mod!(rw, stval, 0x143);
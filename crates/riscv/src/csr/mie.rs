@@ -0,0 +1,36 @@
+//! The `mie` CSR.
+
+read_csr!(0x304);
+write_csr!(0x304);
+set_csr!(0x304);
+clear_csr!(0x304);
+
+/// Read the raw bits of the `mie` CSR.
+pub fn read() -> usize {
+    unsafe { _read() }
+}
+
+/// Write the raw bits into the `mie` CSR.
+pub fn write(bits: usize) {
+    unsafe { _write(bits) }
+}
+
+/// Enable machine software interrupts.
+pub fn set_msoft() {
+    unsafe { _set(1 << 3) }
+}
+
+/// Disable machine software interrupts.
+pub fn clear_msoft() {
+    unsafe { _clear(1 << 3) }
+}
+
+/// Enable machine timer interrupts.
+pub fn set_mtimer() {
+    unsafe { _set(1 << 7) }
+}
+
+/// Disable machine timer interrupts.
+pub fn clear_mtimer() {
+    unsafe { _clear(1 << 7) }
+}
@@ -0,0 +1,30 @@
+//! The `mip` CSR.
+
+read_csr!(0x344);
+set_csr!(0x344);
+clear_csr!(0x344);
+
+/// Read the raw bits of the `mip` CSR.
+pub fn read() -> usize {
+    unsafe { _read() }
+}
+
+/// Raise a supervisor software interrupt on this hart.
+pub fn set_ssoft() {
+    unsafe { _set(1 << 1) }
+}
+
+/// Clear a pending supervisor software interrupt.
+pub fn clear_ssoft() {
+    unsafe { _clear(1 << 1) }
+}
+
+/// Raise a supervisor timer interrupt on this hart.
+pub fn set_stimer() {
+    unsafe { _set(1 << 5) }
+}
+
+/// Clear a pending supervisor timer interrupt.
+pub fn clear_stimer() {
+    unsafe { _clear(1 << 5) }
+}
@@ -4,11 +4,41 @@ write_csr!(0x180);
 read_csr!(0x180);
 
 /// The paging mode to set inside the satp register.
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Mode {
     Bare,
     Sv39,
     Sv48,
+    Sv57,
+    Sv64,
+    /// A reserved or custom mode, with the raw value of the `MODE` field.
+    Other(u8),
+}
+
+impl Mode {
+    /// Convert the raw value of the `MODE` field into a mode.
+    pub fn from_bits(bits: u8) -> Self {
+        match bits {
+            0 => Mode::Bare,
+            8 => Mode::Sv39,
+            9 => Mode::Sv48,
+            10 => Mode::Sv57,
+            11 => Mode::Sv64,
+            bits => Mode::Other(bits & 0xF),
+        }
+    }
+
+    /// Return the raw value of the `MODE` field for this mode.
+    pub fn bits(self) -> u8 {
+        match self {
+            Mode::Bare => 0,
+            Mode::Sv39 => 8,
+            Mode::Sv48 => 9,
+            Mode::Sv57 => 10,
+            Mode::Sv64 => 11,
+            Mode::Other(bits) => bits & 0xF,
+        }
+    }
 }
 
 /// An abstraction around the bitfield of the `satp` register.
@@ -23,15 +53,8 @@ pub struct Satp {
 pub fn read() -> Satp {
     let bits = unsafe { _read() };
 
-    let mode = match bits >> 60 {
-        0 => Mode::Bare,
-        8 => Mode::Sv39,
-        9 => Mode::Sv48,
-        _ => panic!("unimplemented page table mode"),
-    };
-
     Satp {
-        mode,
+        mode: Mode::from_bits((bits >> 60) as u8),
         asid: ((bits >> 44) & 0xFFFF) as u16,
         root_table: ((bits & 0xFFF_FFFF_FFFF) << 12) as u64,
     }
@@ -40,13 +63,6 @@ pub fn read() -> Satp {
 /// Write to the `satp` CSR.
 pub fn write(satp: Satp) {
     let bits = (satp.root_table >> 12) | ((satp.asid as u64) << 44);
-
-    let mode = match satp.mode {
-        Mode::Bare => 0,
-        Mode::Sv39 => 8,
-        Mode::Sv48 => 9,
-    };
-
-    let bits = bits | (mode << 60);
+    let bits = bits | ((satp.mode.bits() as u64) << 60);
     unsafe { _write(bits as usize) }
 }
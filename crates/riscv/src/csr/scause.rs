@@ -0,0 +1,53 @@
+//! The `scause` CSR.
+
+use crate::{trap::Trap, BitField};
+
+read_csr!(0x142);
+write_csr!(0x142);
+
+/// An abstraction around the bitfield of the `scause` register.
+#[derive(Clone, Copy, Debug)]
+pub struct Scause {
+    bits: usize,
+}
+
+impl Scause {
+    /// Return the raw bits of this register.
+    pub fn bits(self) -> usize {
+        self.bits
+    }
+
+    /// Check if the trap was caused by an interrupt.
+    pub fn is_interrupt(self) -> bool {
+        self.bits.get_bit(usize::BITS as usize - 1)
+    }
+
+    /// Check if the trap was caused by an exception.
+    pub fn is_exception(self) -> bool {
+        !self.is_interrupt()
+    }
+
+    /// Return the exception code, without the interrupt bit.
+    pub fn code(self) -> usize {
+        self.bits.get_bits(0..usize::BITS as usize - 1)
+    }
+
+    /// Decode the cause into a [`Trap`].
+    ///
+    /// Returns [`Trap::Reserved`] for unknown causes.
+    pub fn cause(self) -> Trap {
+        Trap::from_cause(self.bits).unwrap_or(Trap::Reserved)
+    }
+}
+
+/// Read from the `scause` CSR.
+pub fn read() -> Scause {
+    Scause {
+        bits: unsafe { _read() },
+    }
+}
+
+/// Write the raw bits into the `scause` CSR.
+pub fn write(bits: usize) {
+    unsafe { _write(bits) }
+}
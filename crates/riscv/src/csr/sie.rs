@@ -0,0 +1,78 @@
+//! The `sie` CSR.
+
+use crate::BitField;
+
+read_csr!(0x104);
+write_csr!(0x104);
+set_csr!(0x104);
+clear_csr!(0x104);
+
+/// An abstraction around the bitfield of the `sie` register.
+#[derive(Clone, Copy, Debug)]
+pub struct Sie {
+    bits: usize,
+}
+
+impl Sie {
+    /// Return the raw bits of this register.
+    pub fn bits(self) -> usize {
+        self.bits
+    }
+
+    /// Check if supervisor software interrupts are enabled.
+    pub fn ssoft(self) -> bool {
+        self.bits.get_bit(1)
+    }
+
+    /// Check if supervisor timer interrupts are enabled.
+    pub fn stimer(self) -> bool {
+        self.bits.get_bit(5)
+    }
+
+    /// Check if supervisor external interrupts are enabled.
+    pub fn sext(self) -> bool {
+        self.bits.get_bit(9)
+    }
+}
+
+/// Read from the `sie` CSR.
+pub fn read() -> Sie {
+    Sie {
+        bits: unsafe { _read() },
+    }
+}
+
+/// Write the raw bits into the `sie` CSR.
+pub fn write(bits: usize) {
+    unsafe { _write(bits) }
+}
+
+/// Enable supervisor software interrupts.
+pub fn set_ssoft() {
+    unsafe { _set(1 << 1) }
+}
+
+/// Disable supervisor software interrupts.
+pub fn clear_ssoft() {
+    unsafe { _clear(1 << 1) }
+}
+
+/// Enable supervisor timer interrupts.
+pub fn set_stimer() {
+    unsafe { _set(1 << 5) }
+}
+
+/// Disable supervisor timer interrupts.
+pub fn clear_stimer() {
+    unsafe { _clear(1 << 5) }
+}
+
+/// Enable supervisor external interrupts.
+pub fn set_sext() {
+    unsafe { _set(1 << 9) }
+}
+
+/// Disable supervisor external interrupts.
+pub fn clear_sext() {
+    unsafe { _clear(1 << 9) }
+}
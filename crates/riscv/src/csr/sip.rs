@@ -0,0 +1,55 @@
+//! The `sip` CSR.
+
+use crate::BitField;
+
+read_csr!(0x144);
+set_csr!(0x144);
+clear_csr!(0x144);
+
+/// An abstraction around the bitfield of the `sip` register.
+#[derive(Clone, Copy, Debug)]
+pub struct Sip {
+    bits: usize,
+}
+
+impl Sip {
+    /// Return the raw bits of this register.
+    pub fn bits(self) -> usize {
+        self.bits
+    }
+
+    /// Check if a supervisor software interrupt is pending.
+    pub fn ssoft(self) -> bool {
+        self.bits.get_bit(1)
+    }
+
+    /// Check if a supervisor timer interrupt is pending.
+    pub fn stimer(self) -> bool {
+        self.bits.get_bit(5)
+    }
+
+    /// Check if a supervisor external interrupt is pending.
+    pub fn sext(self) -> bool {
+        self.bits.get_bit(9)
+    }
+}
+
+/// Read from the `sip` CSR.
+pub fn read() -> Sip {
+    Sip {
+        bits: unsafe { _read() },
+    }
+}
+
+/// Raise a supervisor software interrupt on this hart.
+pub fn set_ssoft() {
+    unsafe { _set(1 << 1) }
+}
+
+/// Clear a pending supervisor software interrupt.
+///
+/// The timer and external interrupt pending bits are read-only in
+/// supervisor mode, and must be cleared by the SBI or the interrupt controller.
+pub fn clear_ssoft() {
+    unsafe { _clear(1 << 1) }
+}
@@ -0,0 +1,149 @@
+//! The `sstatus` CSR.
+
+use crate::BitField;
+
+read_csr!(0x100);
+write_csr!(0x100);
+set_csr!(0x100);
+clear_csr!(0x100);
+
+/// The privilege mode a hart was executing in before entering supervisor mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PreviousMode {
+    User,
+    Supervisor,
+}
+
+/// The state of the floating point unit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FloatState {
+    Off,
+    Initial,
+    Clean,
+    Dirty,
+}
+
+/// An abstraction around the bitfield of the `sstatus` register.
+#[derive(Clone, Copy, Debug)]
+pub struct Sstatus {
+    bits: usize,
+}
+
+impl Sstatus {
+    /// Return the raw bits of this register.
+    pub fn bits(self) -> usize {
+        self.bits
+    }
+
+    /// Check if supervisor interrupts are enabled.
+    pub fn sie(self) -> bool {
+        self.bits.get_bit(1)
+    }
+
+    /// Set the supervisor interrupt enable bit.
+    pub fn set_sie(&mut self, value: bool) {
+        self.bits.set_bit(1, value);
+    }
+
+    /// Check if supervisor interrupts were enabled before the trap was taken.
+    pub fn spie(self) -> bool {
+        self.bits.get_bit(5)
+    }
+
+    /// Set the supervisor previous interrupt enable bit.
+    pub fn set_spie(&mut self, value: bool) {
+        self.bits.set_bit(5, value);
+    }
+
+    /// Return the privilege mode the hart was in before the trap was taken.
+    pub fn spp(self) -> PreviousMode {
+        if self.bits.get_bit(8) {
+            PreviousMode::Supervisor
+        } else {
+            PreviousMode::User
+        }
+    }
+
+    /// Set the privilege mode that will be entered when executing `sret`.
+    pub fn set_spp(&mut self, mode: PreviousMode) {
+        self.bits.set_bit(8, mode == PreviousMode::Supervisor);
+    }
+
+    /// Return the state of the floating point unit.
+    pub fn fs(self) -> FloatState {
+        match self.bits.get_bits(13..15) {
+            0 => FloatState::Off,
+            1 => FloatState::Initial,
+            2 => FloatState::Clean,
+            _ => FloatState::Dirty,
+        }
+    }
+
+    /// Set the state of the floating point unit.
+    pub fn set_fs(&mut self, state: FloatState) {
+        let bits = match state {
+            FloatState::Off => 0,
+            FloatState::Initial => 1,
+            FloatState::Clean => 2,
+            FloatState::Dirty => 3,
+        };
+        self.bits.set_bits(13..15, bits);
+    }
+
+    /// Check if supervisor mode is permitted to access user memory.
+    pub fn sum(self) -> bool {
+        self.bits.get_bit(18)
+    }
+
+    /// Set the permit supervisor user memory access bit.
+    pub fn set_sum(&mut self, value: bool) {
+        self.bits.set_bit(18, value);
+    }
+
+    /// Check if loads from executable-only pages are permitted.
+    pub fn mxr(self) -> bool {
+        self.bits.get_bit(19)
+    }
+
+    /// Set the make executable readable bit.
+    pub fn set_mxr(&mut self, value: bool) {
+        self.bits.set_bit(19, value);
+    }
+
+    /// Check if either the floating point unit, or any other extension has dirty state.
+    pub fn sd(self) -> bool {
+        self.bits.get_bit(63)
+    }
+}
+
+/// Read from the `sstatus` CSR.
+pub fn read() -> Sstatus {
+    Sstatus {
+        bits: unsafe { _read() },
+    }
+}
+
+/// Write to the `sstatus` CSR.
+pub fn write(sstatus: Sstatus) {
+    unsafe { _write(sstatus.bits) }
+}
+
+/// Enable supervisor interrupts on this hart.
+pub fn set_sie() {
+    unsafe { _set(1 << 1) }
+}
+
+/// Disable supervisor interrupts on this hart.
+pub fn clear_sie() {
+    unsafe { _clear(1 << 1) }
+}
+
+/// Permit supervisor mode to access user memory.
+pub fn set_sum() {
+    unsafe { _set(1 << 18) }
+}
+
+/// Forbid supervisor mode to access user memory.
+pub fn clear_sum() {
+    unsafe { _clear(1 << 18) }
+}
@@ -0,0 +1,61 @@
+//! The `stvec` CSR.
+
+use crate::BitField;
+
+read_csr!(0x105);
+write_csr!(0x105);
+
+/// The mode of the trap vector.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// All traps set `pc` to the base address.
+    Direct,
+    /// Interrupts set `pc` to `base + 4 * cause`.
+    Vectored,
+}
+
+/// An abstraction around the bitfield of the `stvec` register.
+#[derive(Clone, Copy, Debug)]
+pub struct Stvec {
+    bits: usize,
+}
+
+impl Stvec {
+    /// Return the raw bits of this register.
+    pub fn bits(self) -> usize {
+        self.bits
+    }
+
+    /// Return the base address of the trap vector.
+    pub fn address(self) -> usize {
+        self.bits & !0b11
+    }
+
+    /// Return the mode of the trap vector.
+    pub fn mode(self) -> Mode {
+        match self.bits.get_bits(0..2) {
+            0 => Mode::Direct,
+            _ => Mode::Vectored,
+        }
+    }
+}
+
+/// Read from the `stvec` CSR.
+pub fn read() -> Stvec {
+    Stvec {
+        bits: unsafe { _read() },
+    }
+}
+
+/// Write the address of the trap vector, and the mode, into the `stvec` CSR.
+///
+/// The address must be aligned to 4 bytes.
+pub fn write(addr: usize, mode: Mode) {
+    assert!(addr & 0b11 == 0, "trap vector must be aligned to 4 bytes");
+
+    let mode = match mode {
+        Mode::Direct => 0,
+        Mode::Vectored => 1,
+    };
+    unsafe { _write(addr | mode) }
+}
@@ -4,6 +4,12 @@
 /// an interrupt.
 pub const INTERRUPT_BIT: usize = 1 << (usize::BITS - 1);
 
+/// The ABI names of all registers inside [`TrapFrame::xregs`].
+pub const XREG_NAMES: [&str; 31] = [
+    "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4", "a5", "a6",
+    "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4", "t5", "t6",
+];
+
 /// The context that is passed to the trap handler.
 /// It stores all registers that will be restored after
 /// the trap handler returned.
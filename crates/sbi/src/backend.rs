@@ -0,0 +1,68 @@
+//! The backend that is used to perform the actual SBI calls.
+//!
+//! On RISC-V, every call is an `ecall` into the SBI implementation. On every other
+//! architecture, the calls are forwarded to the `mock` firmware, so this crate,
+//! and code that depends on it, can be compiled and tested on the host.
+
+use crate::{Error, SbiResult};
+
+/// The raw values that are returned by a SBI call in `a0` and `a1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SbiRet {
+    /// The error code, which is `0` if the call was successful.
+    pub error: isize,
+    /// The return value, which is only valid if the call was successful.
+    pub value: usize,
+}
+
+impl SbiRet {
+    /// Create a new return value out of the raw error code and value.
+    pub const fn new(error: isize, value: usize) -> Self {
+        Self { error, value }
+    }
+
+    /// Create a successful return value.
+    pub const fn ok(value: usize) -> Self {
+        Self { error: 0, value }
+    }
+
+    /// Create a return value for the given error.
+    pub fn err(err: Error) -> Self {
+        Self {
+            error: err.code(),
+            value: 0,
+        }
+    }
+
+    /// Convert the raw values into a [`SbiResult`].
+    pub fn into_result(self) -> SbiResult<usize> {
+        Error::from_sbi_call(self.value, self.error)
+    }
+}
+
+/// Call the function `fid` of the extension `ext`, and pass the arguments in `a0` to `a5`.
+#[cfg(target_arch = "riscv64")]
+#[inline]
+pub fn call(ext: u32, fid: u32, args: [usize; 6]) -> SbiRet {
+    let (error, value): (isize, usize);
+    unsafe {
+        asm!("ecall",
+            inlateout("a0") args[0] => error,
+            inlateout("a1") args[1] => value,
+            in("a2") args[2],
+            in("a3") args[3],
+            in("a4") args[4],
+            in("a5") args[5],
+            in("a6") fid as usize,
+            in("a7") ext as usize,
+        );
+    }
+
+    SbiRet { error, value }
+}
+
+/// Call the function `fid` of the extension `ext`, and pass the arguments in `a0` to `a5`.
+#[cfg(not(target_arch = "riscv64"))]
+pub fn call(ext: u32, fid: u32, args: [usize; 6]) -> SbiRet {
+    crate::mock::call(ext, fid, args)
+}
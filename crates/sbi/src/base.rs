@@ -1,22 +1,12 @@
 //! Function to access the SBI base extension functionality.
 
-use super::{Error, SbiResult};
+use super::{backend, SbiResult};
 
 /// The unique id of the base extension.
 pub const EXTENSION_ID: u32 = 0x10;
 
 fn sbi_call(fid: u32) -> SbiResult<usize> {
-    let (value, err_code): (usize, isize);
-    unsafe {
-        asm!("ecall",
-            inout("a7") EXTENSION_ID => _,
-            inout("a6") fid => _,
-            out("a0") err_code,
-            out("a1") value
-        );
-    }
-
-    Error::from_sbi_call(value, err_code)
+    backend::call(EXTENSION_ID, fid, [0; 6]).into_result()
 }
 
 /// Makes an `ecall` that will get the version
@@ -43,17 +33,9 @@ pub fn impl_version() -> SbiResult<usize> {
 
 /// Checks if the given extension id is available.
 pub fn probe_ext(ext: u32) -> SbiResult<bool> {
-    let (value, err_code): (usize, usize);
-    unsafe {
-        asm!("ecall",
-            inout("a7") EXTENSION_ID => _,
-            inout("a6") 0x03 => _,
-            inout("a0") ext as usize => err_code,
-            out("a1") value,
-        );
-    }
-
-    Error::from_sbi_call(value, err_code as isize).map(|result| result != 0)
+    backend::call(EXTENSION_ID, 0x03, [ext as usize, 0, 0, 0, 0, 0])
+        .into_result()
+        .map(|result| result != 0)
 }
 
 /// Returns the value of the `mvendorid` CSR.
@@ -0,0 +1,98 @@
+//! Function to access the SBI CPPC (Collaborative Processor Performance Control)
+//! extension functionality.
+
+use super::{backend, SbiResult};
+
+/// The unique id of the CPPC extension.
+pub const EXTENSION_ID: u32 = 0x43505043;
+
+/// A CPPC register, as defined by the ACPI specification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Register {
+    HighestPerformance,
+    NominalPerformance,
+    LowestNonlinearPerformance,
+    LowestPerformance,
+    GuaranteedPerformance,
+    DesiredPerformance,
+    MinimumPerformance,
+    MaximumPerformance,
+    PerformanceReductionTolerance,
+    TimeWindow,
+    CounterWraparoundTime,
+    ReferencePerformanceCounter,
+    DeliveredPerformanceCounter,
+    PerformanceLimited,
+    CppcEnable,
+    AutonomousSelectionEnable,
+    AutonomousActivityWindow,
+    EnergyPerformancePreference,
+    ReferencePerformance,
+    LowestFrequency,
+    NominalFrequency,
+    /// The maximum latency, in nanoseconds, to switch between performance levels.
+    TransitionLatency,
+    /// Raw number that will be passed as the register id.
+    Custom(u32),
+}
+
+impl Register {
+    /// The raw register id.
+    pub fn id(self) -> u32 {
+        match self {
+            Register::HighestPerformance => 0x00,
+            Register::NominalPerformance => 0x01,
+            Register::LowestNonlinearPerformance => 0x02,
+            Register::LowestPerformance => 0x03,
+            Register::GuaranteedPerformance => 0x04,
+            Register::DesiredPerformance => 0x05,
+            Register::MinimumPerformance => 0x06,
+            Register::MaximumPerformance => 0x07,
+            Register::PerformanceReductionTolerance => 0x08,
+            Register::TimeWindow => 0x09,
+            Register::CounterWraparoundTime => 0x0A,
+            Register::ReferencePerformanceCounter => 0x0B,
+            Register::DeliveredPerformanceCounter => 0x0C,
+            Register::PerformanceLimited => 0x0D,
+            Register::CppcEnable => 0x0E,
+            Register::AutonomousSelectionEnable => 0x0F,
+            Register::AutonomousActivityWindow => 0x10,
+            Register::EnergyPerformancePreference => 0x11,
+            Register::ReferencePerformance => 0x12,
+            Register::LowestFrequency => 0x13,
+            Register::NominalFrequency => 0x14,
+            Register::TransitionLatency => 0x8000_0000,
+            Register::Custom(id) => id,
+        }
+    }
+}
+
+/// Returns the width of the given register in bits, or `0` if it's not implemented.
+pub fn probe(reg: Register) -> SbiResult<usize> {
+    backend::call(EXTENSION_ID, 0x00, [reg.id() as usize, 0, 0, 0, 0, 0]).into_result()
+}
+
+/// Reads the value of the given register.
+///
+/// On 32 bit systems, this only returns the lower 32 bits of the register.
+pub fn read(reg: Register) -> SbiResult<usize> {
+    backend::call(EXTENSION_ID, 0x01, [reg.id() as usize, 0, 0, 0, 0, 0]).into_result()
+}
+
+/// Reads the upper 32 bits of the given register.
+///
+/// This is only supported on 32 bit systems.
+pub fn read_hi(reg: Register) -> SbiResult<usize> {
+    backend::call(EXTENSION_ID, 0x02, [reg.id() as usize, 0, 0, 0, 0, 0]).into_result()
+}
+
+/// Writes the value into the given register.
+pub fn write(reg: Register, val: u64) -> SbiResult<()> {
+    backend::call(
+        EXTENSION_ID,
+        0x03,
+        [reg.id() as usize, val as usize, 0, 0, 0, 0],
+    )
+    .into_result()
+    .map(|_| ())
+}
@@ -0,0 +1,36 @@
+//! Function to access the SBI Debug Console extension functionality.
+
+use super::{backend, SbiResult};
+
+/// The unique id of the Debug Console extension.
+pub const EXTENSION_ID: u32 = 0x4442434E;
+
+/// Write the bytes at the given physical address to the debug console.
+///
+/// Returns the number of bytes that were written, which may be less than `num_bytes`.
+///
+/// # Safety
+///
+/// `base_addr` must be the physical address of `num_bytes` readable bytes.
+pub unsafe fn console_write(num_bytes: usize, base_addr: usize) -> SbiResult<usize> {
+    // the upper bits of the address are only used on 32 bit systems
+    backend::call(EXTENSION_ID, 0x00, [num_bytes, base_addr, 0, 0, 0, 0]).into_result()
+}
+
+/// Read bytes from the debug console into the given physical address, without blocking.
+///
+/// Returns the number of bytes that were read, which may be `0` if there's no input.
+///
+/// # Safety
+///
+/// `base_addr` must be the physical address of `num_bytes` writable bytes.
+pub unsafe fn console_read(num_bytes: usize, base_addr: usize) -> SbiResult<usize> {
+    backend::call(EXTENSION_ID, 0x01, [num_bytes, base_addr, 0, 0, 0, 0]).into_result()
+}
+
+/// Write a single byte to the debug console, blocking until it was written.
+pub fn console_write_byte(byte: u8) -> SbiResult<()> {
+    backend::call(EXTENSION_ID, 0x02, [byte as usize, 0, 0, 0, 0, 0])
+        .into_result()
+        .map(|_| ())
+}
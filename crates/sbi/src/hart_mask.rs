@@ -0,0 +1,112 @@
+//! The hart mask that is used by SBI calls to select multiple harts.
+
+/// A set of harts, in the format that is used by SBI calls.
+///
+/// Bit `n` of the `mask` selects the hart with the id `base + n`.
+/// A `base` of `usize::MAX` selects all harts and ignores the mask.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HartMask {
+    mask: usize,
+    base: usize,
+}
+
+impl HartMask {
+    /// The number of harts that can be selected by a single mask.
+    pub const BITS: usize = core::mem::size_of::<usize>() * 8;
+
+    /// Create a hart mask from the raw `hart_mask` and `hart_mask_base` values.
+    pub const fn new(mask: usize, base: usize) -> Self {
+        Self { mask, base }
+    }
+
+    /// Create a hart mask that doesn't select any hart.
+    pub const fn empty() -> Self {
+        Self { mask: 0, base: 0 }
+    }
+
+    /// Create a hart mask that selects all harts in the system.
+    pub const fn all() -> Self {
+        Self {
+            mask: 0,
+            base: usize::MAX,
+        }
+    }
+
+    /// Create a hart mask that only selects the given hart.
+    pub const fn from_hart(hart: usize) -> Self {
+        Self {
+            mask: 1,
+            base: hart,
+        }
+    }
+
+    /// The raw `hart_mask` value.
+    pub fn mask(&self) -> usize {
+        self.mask
+    }
+
+    /// The raw `hart_mask_base` value.
+    pub fn base(&self) -> usize {
+        self.base
+    }
+
+    /// Check if this mask selects all harts.
+    pub fn is_all(&self) -> bool {
+        self.base == usize::MAX
+    }
+
+    /// Check if this mask doesn't select any hart.
+    pub fn is_empty(&self) -> bool {
+        !self.is_all() && self.mask == 0
+    }
+
+    /// Check if the given hart is selected by this mask.
+    pub fn contains(&self, hart: usize) -> bool {
+        if self.is_all() {
+            return true;
+        }
+
+        match hart.checked_sub(self.base) {
+            Some(bit) if bit < Self::BITS => self.mask & (1 << bit) != 0,
+            _ => false,
+        }
+    }
+
+    /// Add the given hart to this mask.
+    ///
+    /// Returns `false` if the hart is outside the range of this mask.
+    pub fn insert(&mut self, hart: usize) -> bool {
+        if self.is_all() {
+            return true;
+        }
+
+        match hart.checked_sub(self.base) {
+            Some(bit) if bit < Self::BITS => {
+                self.mask |= 1 << bit;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Remove the given hart from this mask.
+    ///
+    /// Has no effect if this mask selects all harts.
+    pub fn remove(&mut self, hart: usize) {
+        if let Some(bit) = hart.checked_sub(self.base).filter(|&bit| bit < Self::BITS) {
+            self.mask &= !(1 << bit);
+        }
+    }
+
+    /// Return an iterator over the ids of all selected harts.
+    ///
+    /// A mask that selects all harts yields nothing, because the ids are unknown.
+    pub fn iter(&self) -> impl Iterator<Item = usize> {
+        let Self { mask, base } = *self;
+        let mask = if self.is_all() { 0 } else { mask };
+
+        (0..Self::BITS)
+            .filter(move |bit| mask & (1 << bit) != 0)
+            .map(move |bit| base + bit)
+    }
+}
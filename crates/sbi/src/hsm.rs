@@ -1,6 +1,6 @@
 //! Function to access the SBI HSM (Hart State Management) extension functionality.
 
-use super::{Error, SbiResult};
+use super::{backend, Error, SbiResult};
 
 /// The unique id of the HSM extension.
 pub const EXTENSION_ID: u32 = 0x48534D;
@@ -10,36 +10,20 @@ pub const EXTENSION_ID: u32 = 0x48534D;
 /// The hart will start execution at the given `start_addr` and the raw value of `arg`
 /// will be put into `a1`.
 pub fn start(hart_id: usize, start_addr: usize, arg: usize) -> SbiResult<()> {
-    let err_code: isize;
-    unsafe {
-        asm!(
-            "ecall",
-            inlateout("a0") hart_id => err_code,
-            inout("a1") start_addr => _,
-            inout("a2") arg => _,
-            inout("a6") 0 => _,
-            inout("a7") EXTENSION_ID => _,
-        );
-    }
-    Error::from_sbi_call((), err_code)
+    backend::call(EXTENSION_ID, 0x00, [hart_id, start_addr, arg, 0, 0, 0])
+        .into_result()
+        .map(|_| ())
 }
 
 /// Stops the current hart.
 ///
 /// This method must be called with Supervisor and User interrupts disabled.
 pub fn stop() -> SbiResult<!> {
-    let err_code: usize;
-    unsafe {
-        asm!("ecall",
-            inout("a7") EXTENSION_ID => _,
-            inout("a6") 0x01 => _,
-            out("a0") err_code,
-        );
-    }
+    let ret = backend::call(EXTENSION_ID, 0x01, [0; 6]);
 
-    match err_code {
+    match ret.error {
         0 => unreachable!("`hart_stop` sbi call should never return if no error occurred."),
-        err => Err(Error::from_code(err as isize)),
+        err => Err(Error::from_code(err)),
     }
 }
 
@@ -54,30 +38,68 @@ pub enum Status {
     StartRequestPending,
     /// Hart was requested to stop.
     StopRequestPending,
+    /// Hart is suspended.
+    Suspended,
+    /// Hart was requested to suspend.
+    SuspendRequestPending,
+    /// Hart received an interrupt, and waits until it can resume.
+    ResumePending,
     /// Unknown status code.
     Unknown(usize),
 }
 
 /// Returns the current status of the hart with id `hart_id`.
 pub fn status(hart_id: usize) -> SbiResult<Status> {
-    let (value, err_code): (usize, usize);
-    unsafe {
-        asm!("ecall",
-            inout("a7") EXTENSION_ID => _,
-            inout("a6") 0x02 => _,
-            inout("a0") hart_id => err_code,
-            out("a1") value,
-        );
+    let value = backend::call(EXTENSION_ID, 0x02, [hart_id, 0, 0, 0, 0, 0]).into_result()?;
+
+    Ok(match value {
+        0 => Status::Started,
+        1 => Status::Stopped,
+        2 => Status::StartRequestPending,
+        3 => Status::StopRequestPending,
+        4 => Status::Suspended,
+        5 => Status::SuspendRequestPending,
+        6 => Status::ResumePending,
+        status => Status::Unknown(status),
+    })
+}
+
+/// The suspend state that is entered by [`suspend`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuspendType {
+    /// The default retentive suspend state, which preserves all registers and CSRs.
+    DefaultRetentive,
+    /// The default non-retentive suspend state, which loses the state of the hart.
+    DefaultNonRetentive,
+    /// Raw platform specific suspend type.
+    Custom(u32),
+}
+
+impl SuspendType {
+    /// The raw value that is passed as the suspend type.
+    pub fn raw(self) -> u32 {
+        match self {
+            SuspendType::DefaultRetentive => 0x0000_0000,
+            SuspendType::DefaultNonRetentive => 0x8000_0000,
+            SuspendType::Custom(val) => val,
+        }
     }
 
-    match err_code {
-        0 => match value {
-            0 => Ok(Status::Started),
-            1 => Ok(Status::Stopped),
-            2 => Ok(Status::StartRequestPending),
-            3 => Ok(Status::StopRequestPending),
-            status => Ok(Status::Unknown(status)),
-        },
-        err => Err(Error::from_code(err as isize)),
+    /// Check if the state of the hart is preserved while it's suspended.
+    pub fn is_retentive(self) -> bool {
+        self.raw() & 0x8000_0000 == 0
     }
 }
+
+/// Suspends the current hart, until an interrupt, or a platform specific event arrives.
+///
+/// For a retentive suspend type, this function returns after the hart resumed.
+/// Otherwise, the hart resumes execution at `resume_addr`, in supervisor mode, with
+/// paging and interrupts disabled, the hart id in `a0` and the raw value of `opaque` in `a1`.
+/// The `resume_addr` and `opaque` are ignored for retentive suspend types.
+pub fn suspend(suspend_type: SuspendType, resume_addr: usize, opaque: usize) -> SbiResult<()> {
+    let args = [suspend_type.raw() as usize, resume_addr, opaque, 0, 0, 0];
+    backend::call(EXTENSION_ID, 0x03, args)
+        .into_result()
+        .map(|_| ())
+}
@@ -0,0 +1,182 @@
+//! The machine mode side of the SBI, which handles the `ecall`s that are made
+//! by the supervisor.
+//!
+//! The firmware is responsible for setting up the machine mode trap handler, and
+//! for delegating all supervisor interrupts and exceptions. Inside the trap handler,
+//! it has to forward environment calls from supervisor mode to [`ecall`], and
+//! machine timer and software interrupts to [`handle_timer_interrupt`] and
+//! [`handle_software_interrupt`].
+//!
+//! The boot hart enters supervisor mode using [`boot`], while all other harts
+//! call [`wait_for_start`] and wait until they are started through the HSM extension.
+
+mod base;
+mod hsm;
+mod ipi;
+mod legacy;
+mod rfence;
+mod srst;
+mod timer;
+
+pub use hsm::{boot, wait_for_start};
+
+use crate::{Error, HartMask, Platform, SbiResult};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use riscv::csr::{mie, mip};
+
+/// The maximum number of harts that are supported by this SBI implementation.
+pub const MAX_HARTS: usize = HartMask::BITS;
+
+/// The implementation id that is reported by the base extension.
+///
+/// This is not an officially assigned id, and spells "Wind" in ASCII.
+pub const IMPL_ID: usize = 0x57696E64;
+
+/// The implementation version that is reported by the base extension.
+pub const IMPL_VERSION: usize = 0x0001_0000;
+
+/// The standard extensions that are implemented.
+const EXTENSIONS: &[u32] = &[
+    crate::base::EXTENSION_ID,
+    crate::timer::EXTENSION_ID,
+    crate::ipi::EXTENSION_ID,
+    crate::rfence::EXTENSION_ID,
+    crate::hsm::EXTENSION_ID,
+    crate::system::EXTENSION_ID,
+];
+
+/// Raise a supervisor software interrupt.
+const REQUEST_SSIP: usize = 1 << 0;
+/// Execute a `fence.i` instruction.
+const REQUEST_FENCE_I: usize = 1 << 1;
+/// Flush the whole TLB.
+const REQUEST_SFENCE_VMA: usize = 1 << 2;
+/// Start a stopped hart.
+const REQUEST_START: usize = 1 << 3;
+
+/// The state of a single hart.
+struct Hart {
+    /// Bitmap of requests that were sent by other harts.
+    requests: AtomicUsize,
+    /// The HSM status of this hart.
+    status: AtomicUsize,
+    /// The address and argument that are used to start this hart.
+    start_addr: AtomicUsize,
+    opaque: AtomicUsize,
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const STOPPED_HART: Hart = Hart {
+    requests: AtomicUsize::new(0),
+    status: AtomicUsize::new(hsm::STOPPED),
+    start_addr: AtomicUsize::new(0),
+    opaque: AtomicUsize::new(0),
+};
+
+static HARTS: [Hart; MAX_HARTS] = [STOPPED_HART; MAX_HARTS];
+
+/// Handle an `ecall` that was made by the given hart from supervisor mode.
+///
+/// `regs` contains the `a0` to `a7` registers of the hart, and the result
+/// of the call is written back into `a0` and `a1`.
+/// The caller must advance `mepc` past the `ecall` instruction.
+pub fn ecall(platform: &dyn Platform, hart: usize, regs: &mut [usize; 8]) {
+    let (ext, fid) = (regs[7] as u32, regs[6] as u32);
+    let mut args = [0; 6];
+    args.copy_from_slice(&regs[..6]);
+
+    // legacy extensions only return a single value in `a0`
+    if let Some(value) = legacy::handle_ecall(platform, hart, ext, args) {
+        regs[0] = value;
+        return;
+    }
+
+    let result = match ext {
+        crate::base::EXTENSION_ID => base::handle_ecall(fid, args),
+        crate::timer::EXTENSION_ID => timer::handle_ecall(platform, hart, fid, args),
+        crate::ipi::EXTENSION_ID => ipi::handle_ecall(platform, hart, fid, args),
+        crate::rfence::EXTENSION_ID => rfence::handle_ecall(platform, hart, fid, args),
+        crate::hsm::EXTENSION_ID => hsm::handle_ecall(platform, hart, fid, args),
+        crate::system::EXTENSION_ID => srst::handle_ecall(platform, fid, args),
+        _ => Err(Error::NotSupported),
+    };
+
+    let (err_code, value) = match result {
+        Ok(value) => (0, value),
+        Err(err) => (err.code(), 0),
+    };
+    regs[0] = err_code as usize;
+    regs[1] = value;
+}
+
+/// Handle a machine timer interrupt by forwarding it to supervisor mode.
+pub fn handle_timer_interrupt() {
+    // the interrupt stays pending until the supervisor programs the next timer
+    mie::clear_mtimer();
+    mip::set_stimer();
+}
+
+/// Handle a machine software interrupt by executing all requests
+/// that were sent to the given hart.
+pub fn handle_software_interrupt(platform: &dyn Platform, hart: usize) {
+    platform.clear_ipi(hart);
+    handle_requests(hart);
+}
+
+/// Execute all requests that are pending for the given hart, except for starting it.
+fn handle_requests(hart: usize) {
+    let requests = &HARTS[hart].requests;
+    let pending = requests.load(Ordering::Acquire) & !REQUEST_START;
+
+    if pending & REQUEST_SSIP != 0 {
+        mip::set_ssoft();
+    }
+    if pending & REQUEST_FENCE_I != 0 {
+        riscv::asm::fence_i();
+    }
+    if pending & REQUEST_SFENCE_VMA != 0 {
+        riscv::asm::sfence(None, None);
+    }
+
+    requests.fetch_and(!pending, Ordering::Release);
+}
+
+/// Send a request to every started hart inside the mask.
+///
+/// If `wait` is `true`, this function only returns after all harts executed the request.
+fn send(
+    platform: &dyn Platform,
+    hart: usize,
+    harts: HartMask,
+    request: usize,
+    wait: bool,
+) -> SbiResult<()> {
+    let count = platform.hart_count().min(MAX_HARTS);
+    if harts.iter().any(|hart| hart >= count) {
+        return Err(Error::InvalidParam);
+    }
+
+    let targets = (0..count)
+        .filter(|&target| harts.contains(target))
+        .filter(|&target| {
+            let status = HARTS[target].status.load(Ordering::Acquire);
+            status == hsm::STARTED || status == hsm::SUSPENDED
+        });
+
+    for target in targets.clone() {
+        HARTS[target].requests.fetch_or(request, Ordering::Release);
+        platform.send_ipi(target);
+    }
+
+    if wait {
+        for target in targets {
+            while HARTS[target].requests.load(Ordering::Acquire) & request != 0 {
+                // the target may wait for us at the same time
+                handle_requests(hart);
+                core::hint::spin_loop();
+            }
+        }
+    }
+
+    Ok(())
+}
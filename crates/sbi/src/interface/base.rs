@@ -0,0 +1,21 @@
+//! Base SBI extension implementation
+
+use super::{EXTENSIONS, IMPL_ID, IMPL_VERSION};
+use crate::{Error, SbiResult};
+use riscv::csr::{marchid, mimpid, mvendorid};
+
+/// The implemented version of the SBI specification, which is `0.3`.
+const SPEC_VERSION: usize = 3;
+
+pub(super) fn handle_ecall(fid: u32, args: [usize; 6]) -> SbiResult<usize> {
+    match fid {
+        0x00 => Ok(SPEC_VERSION),
+        0x01 => Ok(IMPL_ID),
+        0x02 => Ok(IMPL_VERSION),
+        0x03 => Ok(EXTENSIONS.contains(&(args[0] as u32)) as usize),
+        0x04 => Ok(mvendorid::read()),
+        0x05 => Ok(marchid::read()),
+        0x06 => Ok(mimpid::read()),
+        _ => Err(Error::NotSupported),
+    }
+}
@@ -0,0 +1,133 @@
+//! HSM SBI extension implementation
+
+use super::{handle_requests, HARTS, MAX_HARTS, REQUEST_START};
+use crate::{Error, Platform, SbiResult};
+use core::sync::atomic::Ordering;
+use riscv::csr::{mie, mip};
+
+pub(super) const STARTED: usize = 0;
+pub(super) const STOPPED: usize = 1;
+pub(super) const START_PENDING: usize = 2;
+pub(super) const SUSPENDED: usize = 4;
+
+pub(super) fn handle_ecall(
+    platform: &dyn Platform,
+    hart: usize,
+    fid: u32,
+    args: [usize; 6],
+) -> SbiResult<usize> {
+    match fid {
+        0x00 => start(platform, args[0], args[1], args[2]).map(|_| 0),
+        0x01 => stop(platform, hart),
+        0x02 => match args[0] {
+            target if target < platform.hart_count().min(MAX_HARTS) => {
+                Ok(HARTS[target].status.load(Ordering::Acquire))
+            }
+            _ => Err(Error::InvalidParam),
+        },
+        0x03 => suspend(hart, args[0] as u32).map(|_| 0),
+        _ => Err(Error::NotSupported),
+    }
+}
+
+fn start(platform: &dyn Platform, target: usize, addr: usize, opaque: usize) -> SbiResult<()> {
+    if target >= platform.hart_count().min(MAX_HARTS) {
+        return Err(Error::InvalidParam);
+    }
+
+    let hart = &HARTS[target];
+    hart.status
+        .compare_exchange(STOPPED, START_PENDING, Ordering::Acquire, Ordering::Relaxed)
+        .map_err(|_| Error::AlreadyAvailable)?;
+
+    hart.start_addr.store(addr, Ordering::Relaxed);
+    hart.opaque.store(opaque, Ordering::Relaxed);
+    hart.requests.fetch_or(REQUEST_START, Ordering::Release);
+    platform.send_ipi(target);
+
+    Ok(())
+}
+
+/// Suspend the current hart until an interrupt arrives.
+///
+/// Only the default retentive suspend type is supported.
+fn suspend(hart: usize, suspend_type: u32) -> SbiResult<()> {
+    if suspend_type != crate::hsm::SuspendType::DefaultRetentive.raw() {
+        return Err(Error::NotSupported);
+    }
+
+    let status = &HARTS[hart].status;
+    status.store(SUSPENDED, Ordering::Release);
+    riscv::asm::wfi();
+    status.store(STARTED, Ordering::Release);
+
+    Ok(())
+}
+
+fn stop(platform: &dyn Platform, hart: usize) -> ! {
+    mie::clear_mtimer();
+    mip::clear_stimer();
+    mip::clear_ssoft();
+
+    HARTS[hart].status.store(STOPPED, Ordering::Release);
+    wait_for_start(platform, hart)
+}
+
+/// Enter supervisor mode on the boot hart, at the given address.
+///
+/// The hart id is passed in `a0` and `opaque` in `a1`, which is usually the
+/// address of the devicetree.
+pub fn boot(hart: usize, addr: usize, opaque: usize) -> ! {
+    HARTS[hart].status.store(STARTED, Ordering::Release);
+    mie::set_msoft();
+
+    unsafe { enter_supervisor(hart, addr, opaque) }
+}
+
+/// Wait until the given hart is started by another hart, and then enter
+/// supervisor mode at the requested address.
+///
+/// This must be called with machine interrupts disabled, and is used for every
+/// hart that is not the boot hart. The stack of the caller is never used again.
+pub fn wait_for_start(platform: &dyn Platform, hart: usize) -> ! {
+    let state = &HARTS[hart];
+    mie::set_msoft();
+
+    loop {
+        // a `wfi` returns as soon as the software interrupt is pending,
+        // even if interrupts are disabled
+        platform.clear_ipi(hart);
+        handle_requests(hart);
+
+        if state.requests.fetch_and(!REQUEST_START, Ordering::Acquire) & REQUEST_START != 0 {
+            let addr = state.start_addr.load(Ordering::Relaxed);
+            let opaque = state.opaque.load(Ordering::Relaxed);
+            state.status.store(STARTED, Ordering::Release);
+
+            unsafe { enter_supervisor(hart, addr, opaque) }
+        }
+
+        riscv::asm::wfi();
+    }
+}
+
+/// Switch to supervisor mode and jump to `addr`, with paging and interrupts disabled.
+unsafe fn enter_supervisor(hart: usize, addr: usize, opaque: usize) -> ! {
+    // `MPP` is set to supervisor mode, and `SIE` is cleared
+    const CLEAR: usize = (0b11 << 11) | (1 << 1);
+    const SET: usize = 0b01 << 11;
+
+    asm!(
+        "csrw mepc, {addr}",
+        "csrc mstatus, {clear}",
+        "csrs mstatus, {set}",
+        "csrw satp, zero",
+        "mret",
+        addr = in(reg) addr,
+        clear = in(reg) CLEAR,
+        set = in(reg) SET,
+        in("a0") hart,
+        in("a1") opaque,
+        options(noreturn),
+    )
+}
@@ -1,9 +1,18 @@
-//! RFENCE SBI extension implementation
+//! IPI SBI extension implementation
 
-use crate::{Error, SbiResult};
+use super::{send, REQUEST_SSIP};
+use crate::{Error, HartMask, Platform, SbiResult};
+
+pub(super) fn handle_ecall(
+    platform: &dyn Platform,
+    hart: usize,
+    fid: u32,
+    args: [usize; 6],
+) -> SbiResult<usize> {
+    let harts = HartMask::new(args[0], args[1]);
 
-pub(super) fn handle_ecall(fid: u32) -> SbiResult<usize> {
     match fid {
+        0x00 => send(platform, hart, harts, REQUEST_SSIP, false).map(|_| 0),
         _ => Err(Error::NotSupported),
     }
 }
@@ -0,0 +1,40 @@
+//! Legacy SBI extensions implementation
+
+use super::timer::set_timer;
+use crate::{
+    legacy::{CONSOLE_GETCHAR_ID, CONSOLE_PUTCHAR_ID, SET_TIMER_ID, SHUTDOWN_ID},
+    system::{Reason, Type},
+    Error, Platform,
+};
+
+/// Handle a call to a legacy extension, which returns a single value in `a0`.
+///
+/// Returns `None` if `ext` is not a legacy extension.
+pub(super) fn handle_ecall(
+    platform: &dyn Platform,
+    hart: usize,
+    ext: u32,
+    args: [usize; 6],
+) -> Option<usize> {
+    let value = match ext {
+        SET_TIMER_ID => {
+            set_timer(platform, hart, args[0] as u64);
+            0
+        }
+        CONSOLE_PUTCHAR_ID => {
+            platform.console_putchar(args[0] as u8);
+            0
+        }
+        CONSOLE_GETCHAR_ID => platform
+            .console_getchar()
+            .map_or(usize::MAX, |byte| byte as usize),
+        SHUTDOWN_ID => match platform.reset(Type::Shutdown, Reason::NoReason) {
+            Ok(never) => never,
+            Err(err) => err.code() as usize,
+        },
+        0x03..=0x0F => Error::NotSupported.code() as usize,
+        _ => return None,
+    };
+
+    Some(value)
+}
@@ -0,0 +1,22 @@
+//! RFENCE SBI extension implementation
+
+use super::{send, REQUEST_FENCE_I, REQUEST_SFENCE_VMA};
+use crate::{Error, HartMask, Platform, SbiResult};
+
+pub(super) fn handle_ecall(
+    platform: &dyn Platform,
+    hart: usize,
+    fid: u32,
+    args: [usize; 6],
+) -> SbiResult<usize> {
+    let harts = HartMask::new(args[0], args[1]);
+
+    // the address range and ASID are ignored, and the whole TLB is flushed instead
+    let request = match fid {
+        0x00 => REQUEST_FENCE_I,
+        0x01 | 0x02 => REQUEST_SFENCE_VMA,
+        _ => return Err(Error::NotSupported),
+    };
+
+    send(platform, hart, harts, request, true).map(|_| 0)
+}
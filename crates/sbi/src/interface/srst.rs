@@ -0,0 +1,36 @@
+//! System Reset SBI extension implementation
+
+use crate::{
+    system::{Reason, Type},
+    Error, Platform, SbiResult,
+};
+
+/// The first reset type, and reason, that is reserved for platform specific values.
+const PLATFORM_SPECIFIC: usize = 0xF000_0000;
+
+pub(super) fn handle_ecall(
+    platform: &dyn Platform,
+    fid: u32,
+    args: [usize; 6],
+) -> SbiResult<usize> {
+    if fid != 0x00 {
+        return Err(Error::NotSupported);
+    }
+
+    let type_ = match args[0] {
+        0x00 => Type::Shutdown,
+        0x01 => Type::ColdReboot,
+        0x02 => Type::WarmReboot,
+        val if val >= PLATFORM_SPECIFIC => Type::Custom(val),
+        _ => return Err(Error::InvalidParam),
+    };
+
+    let reason = match args[1] {
+        0x00 => Reason::NoReason,
+        0x01 => Reason::SystemFailure,
+        val if val >= PLATFORM_SPECIFIC => Reason::Custom(val),
+        _ => return Err(Error::InvalidParam),
+    };
+
+    platform.reset(type_, reason).map(|never| never)
+}
@@ -0,0 +1,27 @@
+//! Timer SBI extension implementation
+
+use crate::{Error, Platform, SbiResult};
+use riscv::csr::{mie, mip};
+
+pub(super) fn handle_ecall(
+    platform: &dyn Platform,
+    hart: usize,
+    fid: u32,
+    args: [usize; 6],
+) -> SbiResult<usize> {
+    match fid {
+        0x00 => {
+            set_timer(platform, hart, args[0] as u64);
+            Ok(0)
+        }
+        _ => Err(Error::NotSupported),
+    }
+}
+
+pub(super) fn set_timer(platform: &dyn Platform, hart: usize, stime: u64) {
+    platform.set_timer(hart, stime);
+
+    // the pending supervisor interrupt is cleared, until the new deadline is reached
+    mip::clear_stimer();
+    mie::set_mtimer();
+}
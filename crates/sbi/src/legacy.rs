@@ -0,0 +1,30 @@
+//! Functions of the legacy SBI extensions, which are deprecated
+//! but still implemented by most SBI implementations.
+
+use super::{backend, Error, SbiResult};
+
+/// The unique id of the legacy set timer extension.
+pub const SET_TIMER_ID: u32 = 0x00;
+
+/// The unique id of the legacy console putchar extension.
+pub const CONSOLE_PUTCHAR_ID: u32 = 0x01;
+
+/// The unique id of the legacy console getchar extension.
+pub const CONSOLE_GETCHAR_ID: u32 = 0x02;
+
+/// The unique id of the legacy shutdown extension.
+pub const SHUTDOWN_ID: u32 = 0x08;
+
+/// Write a single byte to the debug console, blocking until it was written.
+pub fn console_putchar(byte: u8) -> SbiResult<()> {
+    // legacy extensions only return an error code in `a0`
+    let ret = backend::call(CONSOLE_PUTCHAR_ID, 0, [byte as usize, 0, 0, 0, 0, 0]);
+    Error::from_sbi_call((), ret.error)
+}
+
+/// Programs the clock for the next event after `stime`, and clears
+/// the pending timer interrupt.
+pub fn set_timer(stime: u64) -> SbiResult<()> {
+    let ret = backend::call(SET_TIMER_ID, 0, [stime as usize, 0, 0, 0, 0, 0]);
+    Error::from_sbi_call((), ret.error)
+}
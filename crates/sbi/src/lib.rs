@@ -4,21 +4,41 @@
 //! and provides the SBI, and it can be used as the API for accessing
 //! SBI functions.
 //!
+//! On every other architecture than RISC-V, the SBI calls are answered by
+//! the `mock` firmware, so code that uses this crate can be tested on the host.
+//!
 //! [OpenSBI]: https://github.com/riscv/riscv-sbi-doc
 #![deny(rust_2018_idioms, rustdoc::broken_intra_doc_links)]
 #![no_std]
 #![feature(asm, cfg_target_has_atomic, never_type)]
 
-// mod hart_mask;
-// mod interface;
-// pub use interface::ecall;
+#[cfg(target_arch = "riscv64")]
+pub mod interface;
+#[cfg(target_arch = "riscv64")]
+pub use interface::ecall;
+
+#[cfg(target_arch = "riscv64")]
+pub mod platform;
+#[cfg(target_arch = "riscv64")]
+pub use platform::Platform;
 
-// pub mod platform;
-// pub use platform::Platform;
+pub mod backend;
+#[cfg(not(target_arch = "riscv64"))]
+pub mod mock;
+
+pub mod hart_mask;
+pub use hart_mask::HartMask;
 
 pub mod base;
+pub mod cppc;
+pub mod dbcn;
 pub mod hsm;
 pub mod ipi;
+pub mod legacy;
+pub mod pmu;
+pub mod probe;
+pub mod rfence;
+pub mod susp;
 pub mod system;
 pub mod timer;
 
@@ -31,7 +51,7 @@ pub type SbiResult<T> = core::result::Result<T, Error>;
 /// An [`Error`] is retrieved by reading the `a0` register after making
 /// a SBI call. If the register is `0`, the call was successful and there's
 /// probably a value available in `a1`, otherwise the SBI call failed.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[repr(usize)]
 pub enum Error {
     /// The SBI call failed to execute.
@@ -46,6 +66,10 @@ pub enum Error {
     InvalidAddress,
     /// The resource is already available.
     AlreadyAvailable,
+    /// The operation was already started.
+    AlreadyStarted,
+    /// The operation was already stopped.
+    AlreadyStopped,
     /// Unknown SBI error was returned.
     Unknown(isize),
 }
@@ -62,6 +86,8 @@ impl Error {
             -4 => Error::Denied,
             -5 => Error::InvalidAddress,
             -6 => Error::AlreadyAvailable,
+            -7 => Error::AlreadyStarted,
+            -8 => Error::AlreadyStopped,
             code => Error::Unknown(code),
         }
     }
@@ -75,6 +101,8 @@ impl Error {
             Error::Denied => -4,
             Error::InvalidAddress => -5,
             Error::AlreadyAvailable => -6,
+            Error::AlreadyStarted => -7,
+            Error::AlreadyStopped => -8,
             Error::Unknown(code) => code,
         }
     }
@@ -88,3 +116,19 @@ impl Error {
         }
     }
 }
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match *self {
+            Error::Failed => f.write_str("the SBI call failed"),
+            Error::NotSupported => f.write_str("the SBI call is not supported"),
+            Error::InvalidParam => f.write_str("invalid parameter for the SBI call"),
+            Error::Denied => f.write_str("the SBI call was denied"),
+            Error::InvalidAddress => f.write_str("invalid address for the SBI call"),
+            Error::AlreadyAvailable => f.write_str("the resource is already available"),
+            Error::AlreadyStarted => f.write_str("the operation was already started"),
+            Error::AlreadyStopped => f.write_str("the operation was already stopped"),
+            Error::Unknown(code) => write!(f, "unknown SBI error code {}", code),
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! A scripted fake firmware, that replaces the `ecall`s on every architecture except RISC-V.
+//!
+//! Every thread has its own firmware, so tests can run in parallel. Each SBI call
+//! is recorded, and answered by the first scripted response that matches the call,
+//! or by the [handler](set_handler) of the firmware. Calls that have no response
+//! fail with [`Error::NotSupported`], like on a firmware that implements nothing.
+//!
+//! The workspace builds for RISC-V with `build-std` by default, which doesn't work
+//! for the host, so the tests must be run using `tools/host-test`.
+
+extern crate std;
+
+use crate::{backend::SbiRet, Error};
+use std::{cell::RefCell, collections::VecDeque, vec::Vec};
+
+/// A single SBI call that was made.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Call {
+    /// The extension id, that was passed in `a7`.
+    pub ext: u32,
+    /// The function id, that was passed in `a6`.
+    pub fid: u32,
+    /// The arguments, that were passed in `a0` to `a5`.
+    pub args: [usize; 6],
+}
+
+/// A function that answers every SBI call that has no scripted response.
+pub type Handler = fn(&Call) -> SbiRet;
+
+/// A scripted response, that is only used for calls to the given extension and function.
+struct Response {
+    filter: Option<(u32, u32)>,
+    ret: SbiRet,
+}
+
+struct Firmware {
+    script: VecDeque<Response>,
+    calls: Vec<Call>,
+    handler: Option<Handler>,
+}
+
+impl Firmware {
+    fn new() -> Self {
+        Self {
+            script: VecDeque::new(),
+            calls: Vec::new(),
+            handler: None,
+        }
+    }
+}
+
+std::thread_local! {
+    static FIRMWARE: RefCell<Firmware> = RefCell::new(Firmware::new());
+}
+
+/// Answer the next SBI call with the given return value.
+pub fn respond(ret: SbiRet) {
+    push(None, ret);
+}
+
+/// Answer the next SBI call to the given extension and function with the given return value.
+pub fn respond_to(ext: u32, fid: u32, ret: SbiRet) {
+    push(Some((ext, fid)), ret);
+}
+
+/// Let the next SBI call fail with the given error.
+pub fn fail(err: Error) {
+    respond(SbiRet::err(err));
+}
+
+/// Set the handler that answers every SBI call which has no scripted response.
+pub fn set_handler(handler: Option<Handler>) {
+    FIRMWARE.with(|fw| fw.borrow_mut().handler = handler);
+}
+
+/// Return all SBI calls that were made since the last call to this function, or [`reset`].
+pub fn calls() -> Vec<Call> {
+    FIRMWARE.with(|fw| core::mem::take(&mut fw.borrow_mut().calls))
+}
+
+/// Remove all scripted responses, recorded calls and the handler.
+pub fn reset() {
+    FIRMWARE.with(|fw| *fw.borrow_mut() = Firmware::new());
+}
+
+fn push(filter: Option<(u32, u32)>, ret: SbiRet) {
+    FIRMWARE.with(|fw| fw.borrow_mut().script.push_back(Response { filter, ret }));
+}
+
+/// Record the given SBI call, and answer it using the script or the handler.
+pub(crate) fn call(ext: u32, fid: u32, args: [usize; 6]) -> SbiRet {
+    let call = Call { ext, fid, args };
+
+    let (scripted, handler) = FIRMWARE.with(|fw| {
+        let mut fw = fw.borrow_mut();
+        fw.calls.push(call);
+
+        let idx = fw
+            .script
+            .iter()
+            .position(|resp| resp.filter.map_or(true, |filter| filter == (ext, fid)));
+        let scripted = idx
+            .and_then(|idx| fw.script.remove(idx))
+            .map(|resp| resp.ret);
+        (scripted, fw.handler)
+    });
+
+    // the handler is called without borrowing the firmware, so it can script new responses
+    scripted
+        .or_else(|| handler.map(|handler| handler(&call)))
+        .unwrap_or_else(|| SbiRet::err(Error::NotSupported))
+}
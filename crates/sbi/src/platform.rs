@@ -0,0 +1,44 @@
+//! The hardware abstraction that is used when running as the SBI implementation.
+//!
+//! Every board has to provide a [`Platform`], which is then passed to the
+//! [`ecall`](crate::ecall) dispatcher and the machine mode interrupt handlers.
+
+use crate::{
+    system::{Reason, Type},
+    SbiResult,
+};
+
+pub mod qemu_virt;
+pub use qemu_virt::QemuVirt;
+
+/// The machine specific functionality that is required to implement the SBI.
+///
+/// The platform is shared between all harts, so every method may be
+/// called from multiple harts at the same time.
+pub trait Platform: Sync {
+    /// The number of harts in the system.
+    ///
+    /// Hart ids are expected to be in the range `0..hart_count()`.
+    fn hart_count(&self) -> usize;
+
+    /// Write a single byte to the console, and block until it can be sent.
+    fn console_putchar(&self, byte: u8);
+
+    /// Read a single byte from the console, without blocking.
+    fn console_getchar(&self) -> Option<u8>;
+
+    /// Program the timer of the given hart to raise a machine timer
+    /// interrupt as soon as the time reaches `time`.
+    fn set_timer(&self, hart: usize, time: u64);
+
+    /// Raise a machine software interrupt on the given hart.
+    fn send_ipi(&self, hart: usize);
+
+    /// Clear a pending machine software interrupt of the given hart.
+    fn clear_ipi(&self, hart: usize);
+
+    /// Reset, or shut down, the whole system.
+    ///
+    /// Returns an error if the type of reset is not supported.
+    fn reset(&self, type_: Type, reason: Reason) -> SbiResult<!>;
+}
@@ -0,0 +1,106 @@
+//! Platform implementation for the QEMU `virt` machine.
+
+use super::Platform;
+use crate::{
+    system::{Reason, Type},
+    Error, SbiResult,
+};
+use core::ptr;
+
+/// The base address of the CLINT, which provides timer and software interrupts.
+const CLINT_BASE: usize = 0x0200_0000;
+/// Offset of the `msip` register of hart `0`, inside the CLINT.
+const CLINT_MSIP: usize = 0x0000;
+/// Offset of the `mtimecmp` register of hart `0`, inside the CLINT.
+const CLINT_MTIMECMP: usize = 0x4000;
+
+/// The base address of the NS16550A UART.
+const UART_BASE: usize = 0x1000_0000;
+/// Offset of the transmit and receive buffer register.
+const UART_DATA: usize = 0x00;
+/// Offset of the line status register.
+const UART_LSR: usize = 0x05;
+/// Bit inside the line status register, that indicates that there's received data.
+const UART_DATA_READY: u8 = 1 << 0;
+/// Bit inside the line status register, that indicates that a new byte can be sent.
+const UART_THR_EMPTY: u8 = 1 << 5;
+
+/// The address of the `sifive,test` device, which can shut down, or reset QEMU.
+const VIRT_TEST: usize = 0x10_0000;
+const VIRT_TEST_PASS: u32 = 0x5555;
+const VIRT_TEST_FAIL: u32 = 0x3333;
+const VIRT_TEST_RESET: u32 = 0x7777;
+
+/// The QEMU `virt` machine, which uses a CLINT for timer and software interrupts,
+/// a NS16550A UART as the console and the `sifive,test` device for resets.
+#[derive(Debug)]
+pub struct QemuVirt {
+    harts: usize,
+}
+
+impl QemuVirt {
+    /// Create the platform for a machine with the given number of harts.
+    ///
+    /// This is the value of the `-smp` option that was passed to QEMU.
+    pub const fn new(harts: usize) -> Self {
+        Self { harts }
+    }
+}
+
+impl Platform for QemuVirt {
+    fn hart_count(&self) -> usize {
+        self.harts
+    }
+
+    fn console_putchar(&self, byte: u8) {
+        unsafe {
+            while read8(UART_BASE + UART_LSR) & UART_THR_EMPTY == 0 {
+                core::hint::spin_loop();
+            }
+            ptr::write_volatile((UART_BASE + UART_DATA) as *mut u8, byte);
+        }
+    }
+
+    fn console_getchar(&self) -> Option<u8> {
+        unsafe {
+            match read8(UART_BASE + UART_LSR) & UART_DATA_READY {
+                0 => None,
+                _ => Some(read8(UART_BASE + UART_DATA)),
+            }
+        }
+    }
+
+    fn set_timer(&self, hart: usize, time: u64) {
+        let addr = CLINT_BASE + CLINT_MTIMECMP + hart * 8;
+        unsafe { ptr::write_volatile(addr as *mut u64, time) }
+    }
+
+    fn send_ipi(&self, hart: usize) {
+        let addr = CLINT_BASE + CLINT_MSIP + hart * 4;
+        unsafe { ptr::write_volatile(addr as *mut u32, 1) }
+    }
+
+    fn clear_ipi(&self, hart: usize) {
+        let addr = CLINT_BASE + CLINT_MSIP + hart * 4;
+        unsafe { ptr::write_volatile(addr as *mut u32, 0) }
+    }
+
+    fn reset(&self, type_: Type, reason: Reason) -> SbiResult<!> {
+        let status = match (type_, reason) {
+            (Type::Shutdown, Reason::NoReason) => VIRT_TEST_PASS,
+            (Type::Shutdown, _) => (1 << 16) | VIRT_TEST_FAIL,
+            (Type::ColdReboot, _) | (Type::WarmReboot, _) => VIRT_TEST_RESET,
+            (Type::Custom(_), _) => return Err(Error::NotSupported),
+        };
+
+        unsafe { ptr::write_volatile(VIRT_TEST as *mut u32, status) };
+
+        loop {
+            riscv::asm::wfi();
+        }
+    }
+}
+
+unsafe fn read8(addr: usize) -> u8 {
+    ptr::read_volatile(addr as *const u8)
+}
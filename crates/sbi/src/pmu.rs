@@ -0,0 +1,256 @@
+//! Function to access the SBI PMU (Performance Monitoring Unit) extension functionality.
+//!
+//! Counters are selected using a base index and a bit mask, the same way
+//! as harts are selected by a [`HartMask`](crate::HartMask).
+
+use super::{backend, SbiResult};
+
+/// The unique id of the PMU extension.
+pub const EXTENSION_ID: u32 = 0x504D55;
+
+/// Skip the counter matching, and use the counter that is given by the base index.
+pub const CONFIG_SKIP_MATCH: usize = 1 << 0;
+/// Clear the value of the counter after configuring it.
+pub const CONFIG_CLEAR_VALUE: usize = 1 << 1;
+/// Start the counter after configuring it.
+pub const CONFIG_AUTO_START: usize = 1 << 2;
+/// Don't count events in VU-mode.
+pub const CONFIG_SET_VUINH: usize = 1 << 3;
+/// Don't count events in VS-mode.
+pub const CONFIG_SET_VSINH: usize = 1 << 4;
+/// Don't count events in U-mode.
+pub const CONFIG_SET_UINH: usize = 1 << 5;
+/// Don't count events in S-mode.
+pub const CONFIG_SET_SINH: usize = 1 << 6;
+/// Don't count events in M-mode.
+pub const CONFIG_SET_MINH: usize = 1 << 7;
+
+/// Set the value of the counters to the initial value before starting them.
+pub const START_SET_INIT_VALUE: usize = 1 << 0;
+
+/// Release the counters after stopping them, so they can be configured again.
+pub const STOP_RESET: usize = 1 << 0;
+
+/// A generic hardware event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(usize)]
+pub enum HardwareEvent {
+    CpuCycles = 1,
+    Instructions = 2,
+    CacheReferences = 3,
+    CacheMisses = 4,
+    BranchInstructions = 5,
+    BranchMisses = 6,
+    BusCycles = 7,
+    StalledCyclesFrontend = 8,
+    StalledCyclesBackend = 9,
+    RefCpuCycles = 10,
+}
+
+/// The cache that is monitored by a cache event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(usize)]
+pub enum Cache {
+    L1D = 0,
+    L1I = 1,
+    LL = 2,
+    Dtlb = 3,
+    Itlb = 4,
+    Bpu = 5,
+    Node = 6,
+}
+
+/// The operation that is monitored by a cache event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(usize)]
+pub enum CacheOp {
+    Read = 0,
+    Write = 1,
+    Prefetch = 2,
+}
+
+/// The result of the operation that is monitored by a cache event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(usize)]
+pub enum CacheResult {
+    Access = 0,
+    Miss = 1,
+}
+
+/// An event that is caused by the SBI implementation itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(usize)]
+pub enum FirmwareEvent {
+    MisalignedLoad = 0,
+    MisalignedStore = 1,
+    AccessLoad = 2,
+    AccessStore = 3,
+    IllegalInstruction = 4,
+    SetTimer = 5,
+    IpiSent = 6,
+    IpiReceived = 7,
+    FenceISent = 8,
+    FenceIReceived = 9,
+    SfenceVmaSent = 10,
+    SfenceVmaReceived = 11,
+    SfenceVmaAsidSent = 12,
+    SfenceVmaAsidReceived = 13,
+    HfenceGvmaSent = 14,
+    HfenceGvmaReceived = 15,
+    HfenceGvmaVmidSent = 16,
+    HfenceGvmaVmidReceived = 17,
+    HfenceVvmaSent = 18,
+    HfenceVvmaReceived = 19,
+    HfenceVvmaAsidSent = 20,
+    HfenceVvmaAsidReceived = 21,
+}
+
+/// An event that can be monitored by a counter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// A generic hardware event.
+    Hardware(HardwareEvent),
+    /// An access to, or a miss of, a cache.
+    Cache {
+        cache: Cache,
+        op: CacheOp,
+        result: CacheResult,
+    },
+    /// A platform specific hardware event, that is written into the `mhpmeventX` CSR.
+    Raw(u64),
+    /// An event that is caused by the SBI implementation.
+    Firmware(FirmwareEvent),
+}
+
+impl Event {
+    /// The raw `event_idx` value, which contains the type of the event
+    /// in bits `16..20` and the code of the event in bits `0..16`.
+    pub fn idx(&self) -> usize {
+        let (ty, code) = match *self {
+            Event::Hardware(event) => (0, event as usize),
+            Event::Cache { cache, op, result } => (
+                1,
+                (cache as usize) << 3 | (op as usize) << 1 | result as usize,
+            ),
+            Event::Raw(_) => (2, 0),
+            Event::Firmware(event) => (15, event as usize),
+        };
+
+        ty << 16 | code
+    }
+
+    /// The raw `event_data` value, which is only used by raw events.
+    pub fn data(&self) -> u64 {
+        match *self {
+            Event::Raw(data) => data,
+            _ => 0,
+        }
+    }
+}
+
+/// Information about a single counter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CounterInfo(usize);
+
+impl CounterInfo {
+    /// Create counter information from the raw value that is returned by the SBI.
+    pub const fn new(raw: usize) -> Self {
+        Self(raw)
+    }
+
+    /// The raw value of this counter information.
+    pub fn raw(&self) -> usize {
+        self.0
+    }
+
+    /// Check if this is a firmware counter, which must be read using [`counter_fw_read`].
+    pub fn is_firmware(&self) -> bool {
+        self.0 >> (core::mem::size_of::<usize>() * 8 - 1) != 0
+    }
+
+    /// The number of the CSR that can be used to read this hardware counter.
+    pub fn csr(&self) -> u16 {
+        (self.0 & 0xFFF) as u16
+    }
+
+    /// The number of bits of this hardware counter.
+    pub fn width(&self) -> u32 {
+        ((self.0 >> 12) & 0x3F) as u32 + 1
+    }
+}
+
+/// Returns the number of hardware and firmware counters.
+pub fn num_counters() -> SbiResult<usize> {
+    backend::call(EXTENSION_ID, 0x00, [0; 6]).into_result()
+}
+
+/// Returns information about the counter with the given index.
+pub fn counter_get_info(counter_idx: usize) -> SbiResult<CounterInfo> {
+    backend::call(EXTENSION_ID, 0x01, [counter_idx, 0, 0, 0, 0, 0])
+        .into_result()
+        .map(CounterInfo)
+}
+
+/// Find a counter, out of the counters selected by `counter_idx_base`
+/// and `counter_idx_mask`, that can monitor the given event, and configure it.
+///
+/// The `config_flags` are a combination of the `CONFIG_*` constants.
+/// Returns the index of the counter that was configured.
+pub fn counter_config_matching(
+    counter_idx_base: usize,
+    counter_idx_mask: usize,
+    config_flags: usize,
+    event: Event,
+) -> SbiResult<usize> {
+    let args = [
+        counter_idx_base,
+        counter_idx_mask,
+        config_flags,
+        event.idx(),
+        event.data() as usize,
+        0,
+    ];
+    backend::call(EXTENSION_ID, 0x02, args).into_result()
+}
+
+/// Start the counters that are selected by `counter_idx_base` and `counter_idx_mask`.
+///
+/// The `start_flags` are a combination of the `START_*` constants, and `initial_value`
+/// is only used if [`START_SET_INIT_VALUE`] is set.
+pub fn counter_start(
+    counter_idx_base: usize,
+    counter_idx_mask: usize,
+    start_flags: usize,
+    initial_value: u64,
+) -> SbiResult<()> {
+    let args = [
+        counter_idx_base,
+        counter_idx_mask,
+        start_flags,
+        initial_value as usize,
+        0,
+        0,
+    ];
+    backend::call(EXTENSION_ID, 0x03, args)
+        .into_result()
+        .map(|_| ())
+}
+
+/// Stop the counters that are selected by `counter_idx_base` and `counter_idx_mask`.
+///
+/// The `stop_flags` are a combination of the `STOP_*` constants.
+pub fn counter_stop(
+    counter_idx_base: usize,
+    counter_idx_mask: usize,
+    stop_flags: usize,
+) -> SbiResult<()> {
+    let args = [counter_idx_base, counter_idx_mask, stop_flags, 0, 0, 0];
+    backend::call(EXTENSION_ID, 0x04, args)
+        .into_result()
+        .map(|_| ())
+}
+
+/// Returns the current value of the firmware counter with the given index.
+pub fn counter_fw_read(counter_idx: usize) -> SbiResult<usize> {
+    backend::call(EXTENSION_ID, 0x05, [counter_idx, 0, 0, 0, 0, 0]).into_result()
+}
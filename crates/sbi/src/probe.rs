@@ -0,0 +1,143 @@
+//! Finding out which extensions are provided by the SBI implementation.
+
+use crate::{base, cppc, dbcn, hsm, ipi, legacy, pmu, rfence, susp, system, timer};
+
+/// An SBI extension that can be probed using [`probe`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Extension {
+    /// The timer extension.
+    Timer,
+    /// The inter-processor interrupt extension.
+    Ipi,
+    /// The remote fence extension.
+    Rfence,
+    /// The hart state management extension.
+    Hsm,
+    /// The system reset extension.
+    Srst,
+    /// The performance monitoring unit extension.
+    Pmu,
+    /// The debug console extension.
+    Dbcn,
+    /// The system suspend extension.
+    Susp,
+    /// The collaborative processor performance control extension.
+    Cppc,
+    /// The legacy `console_putchar` extension.
+    LegacyConsole,
+    /// The legacy `set_timer` extension.
+    LegacyTimer,
+}
+
+impl Extension {
+    /// All extensions that are probed by [`probe`].
+    pub const ALL: [Extension; 11] = [
+        Extension::Timer,
+        Extension::Ipi,
+        Extension::Rfence,
+        Extension::Hsm,
+        Extension::Srst,
+        Extension::Pmu,
+        Extension::Dbcn,
+        Extension::Susp,
+        Extension::Cppc,
+        Extension::LegacyConsole,
+        Extension::LegacyTimer,
+    ];
+
+    /// The unique id of this extension.
+    pub fn id(self) -> u32 {
+        match self {
+            Extension::Timer => timer::EXTENSION_ID,
+            Extension::Ipi => ipi::EXTENSION_ID,
+            Extension::Rfence => rfence::EXTENSION_ID,
+            Extension::Hsm => hsm::EXTENSION_ID,
+            Extension::Srst => system::EXTENSION_ID,
+            Extension::Pmu => pmu::EXTENSION_ID,
+            Extension::Dbcn => dbcn::EXTENSION_ID,
+            Extension::Susp => susp::EXTENSION_ID,
+            Extension::Cppc => cppc::EXTENSION_ID,
+            Extension::LegacyConsole => legacy::CONSOLE_PUTCHAR_ID,
+            Extension::LegacyTimer => legacy::SET_TIMER_ID,
+        }
+    }
+
+    /// The short name of this extension, as it's used by the SBI specification.
+    pub fn name(self) -> &'static str {
+        match self {
+            Extension::Timer => "TIME",
+            Extension::Ipi => "IPI",
+            Extension::Rfence => "RFENCE",
+            Extension::Hsm => "HSM",
+            Extension::Srst => "SRST",
+            Extension::Pmu => "PMU",
+            Extension::Dbcn => "DBCN",
+            Extension::Susp => "SUSP",
+            Extension::Cppc => "CPPC",
+            Extension::LegacyConsole => "legacy console",
+            Extension::LegacyTimer => "legacy timer",
+        }
+    }
+}
+
+/// A set of [`Extension`]s.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Extensions(u32);
+
+impl Extensions {
+    /// Create a set that contains no extension.
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Create a set from the raw bits, that were returned by [`Extensions::bits`].
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    /// Return the raw bits of this set, where every extension is one bit.
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Add the given extension to this set.
+    pub fn insert(&mut self, ext: Extension) {
+        self.0 |= 1 << ext as u32;
+    }
+
+    /// Check if the given extension is part of this set.
+    pub fn contains(self, ext: Extension) -> bool {
+        self.0 & (1 << ext as u32) != 0
+    }
+
+    /// Return an iterator over all extensions inside this set.
+    pub fn iter(self) -> impl Iterator<Item = Extension> {
+        Extension::ALL
+            .iter()
+            .copied()
+            .filter(move |&ext| self.contains(ext))
+    }
+}
+
+/// Probe every known extension of the SBI implementation.
+///
+/// If the SBI implementation only supports the legacy extensions,
+/// which don't provide a way to probe, they are assumed to exist.
+/// An extension whose probe fails is treated as missing.
+pub fn probe() -> Extensions {
+    let mut extensions = Extensions::empty();
+
+    if base::spec_version().is_err() {
+        extensions.insert(Extension::LegacyConsole);
+        extensions.insert(Extension::LegacyTimer);
+        return extensions;
+    }
+
+    for &ext in Extension::ALL.iter() {
+        if base::probe_ext(ext.id()).unwrap_or(false) {
+            extensions.insert(ext);
+        }
+    }
+
+    extensions
+}
@@ -0,0 +1,39 @@
+//! Function to access the SBI RFENCE (Remote Fence) extension functionality.
+
+use super::{backend, HartMask, SbiResult};
+
+/// The unique id of the RFENCE extension.
+pub const EXTENSION_ID: u32 = 0x52464E43;
+
+/// Instructs the given harts to execute a `fence.i` instruction.
+pub fn remote_fence_i(harts: HartMask) -> SbiResult<()> {
+    backend::call(EXTENSION_ID, 0x00, [harts.mask(), harts.base(), 0, 0, 0, 0])
+        .into_result()
+        .map(|_| ())
+}
+
+/// Instructs the given harts to execute one or more `sfence.vma` instructions,
+/// covering the range of virtual addresses between `start` and `start + size`.
+///
+/// A `start` and `size` of `0`, or a `size` of `usize::MAX`, flushes all addresses.
+pub fn remote_sfence_vma(harts: HartMask, start: usize, size: usize) -> SbiResult<()> {
+    let args = [harts.mask(), harts.base(), start, size, 0, 0];
+    backend::call(EXTENSION_ID, 0x01, args)
+        .into_result()
+        .map(|_| ())
+}
+
+/// Instructs the given harts to execute one or more `sfence.vma` instructions,
+/// covering the range of virtual addresses between `start` and `start + size`,
+/// but only for the given address space.
+pub fn remote_sfence_vma_asid(
+    harts: HartMask,
+    start: usize,
+    size: usize,
+    asid: usize,
+) -> SbiResult<()> {
+    let args = [harts.mask(), harts.base(), start, size, asid, 0];
+    backend::call(EXTENSION_ID, 0x02, args)
+        .into_result()
+        .map(|_| ())
+}
@@ -0,0 +1,37 @@
+//! Function to access the SBI System Suspend extension functionality.
+
+use super::{backend, Error, SbiResult};
+
+/// The unique id of the System Suspend extension.
+pub const EXTENSION_ID: u32 = 0x53555350;
+
+/// The sleep state the system enters in a `suspend` SBI call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SleepType {
+    /// Suspend to RAM, where the memory is preserved.
+    SuspendToRam,
+    /// Raw number that will be passed to the sleep type argument.
+    Custom(u32),
+}
+
+/// Suspends the whole system into the given sleep state.
+///
+/// All other harts must be stopped before. When the system wakes up, the current hart
+/// resumes execution at `resume_addr`, in supervisor mode, with paging and interrupts
+/// disabled, the hart id in `a0` and the raw value of `opaque` in `a1`.
+///
+/// This function only returns if the system couldn't be suspended.
+pub fn suspend(sleep_type: SleepType, resume_addr: usize, opaque: usize) -> SbiResult<!> {
+    let sleep_type = match sleep_type {
+        SleepType::SuspendToRam => 0x00,
+        SleepType::Custom(val) => val,
+    };
+
+    let args = [sleep_type as usize, resume_addr, opaque, 0, 0, 0];
+    let ret = backend::call(EXTENSION_ID, 0x00, args);
+
+    match ret.error {
+        0 => unreachable!("`system_suspend` sbi call should never return if no error occurred."),
+        err => Err(Error::from_code(err)),
+    }
+}
@@ -1,6 +1,6 @@
 //! Function to access the SBI System Reset extension functionality.
 
-use super::{Error, SbiResult};
+use super::{backend, Error, SbiResult};
 
 /// The unique id of the System Reset extension.
 pub const EXTENSION_ID: u32 = 0x53525354;
@@ -26,8 +26,6 @@ pub enum Reason {
 
 /// Reset the system based on the provided arguments.
 pub fn reset(type_: Type, reason: Reason) -> SbiResult<!> {
-    let err_code: usize;
-
     let type_ = match type_ {
         Type::Shutdown => 0x00,
         Type::ColdReboot => 0x01,
@@ -41,19 +39,11 @@ pub fn reset(type_: Type, reason: Reason) -> SbiResult<!> {
         Reason::Custom(val) => val,
     };
 
-    unsafe {
-        asm!("ecall",
-            inout("a7") EXTENSION_ID => _,
-            inout("a6") 0x00 => _,
-
-            inout("a0") type_ => err_code,
-            inout("a1") reason => _,
-        );
-    }
+    let ret = backend::call(EXTENSION_ID, 0x00, [type_, reason, 0, 0, 0, 0]);
 
-    match err_code {
+    match ret.error {
         0 => unreachable!("`system_reset` sbi call should never return if no error occurred."),
-        err => Err(Error::from_code(err as isize)),
+        err => Err(Error::from_code(err)),
     }
 }
 
@@ -0,0 +1,97 @@
+//! Tests for the SBI calls, which are answered by the mock firmware on the host.
+
+use sbi::{
+    backend::SbiRet,
+    mock::{self, Call},
+    Error,
+};
+
+#[test]
+fn decodes_every_error_code() {
+    let errors = [
+        (-1, Error::Failed),
+        (-2, Error::NotSupported),
+        (-3, Error::InvalidParam),
+        (-4, Error::Denied),
+        (-5, Error::InvalidAddress),
+        (-6, Error::AlreadyAvailable),
+        (-7, Error::AlreadyStarted),
+        (-8, Error::AlreadyStopped),
+        (-42, Error::Unknown(-42)),
+    ];
+
+    mock::reset();
+    for (code, err) in errors.iter().cloned() {
+        mock::respond(SbiRet::new(code, 0));
+        assert_eq!(sbi::base::impl_id(), Err(err.clone()));
+        assert_eq!(err.code(), code);
+
+        mock::fail(err.clone());
+        assert_eq!(sbi::timer::set_timer(0), Err(err));
+    }
+}
+
+#[test]
+fn decodes_successful_calls() {
+    mock::reset();
+    mock::respond(SbiRet::ok(0x1234));
+    assert_eq!(sbi::base::impl_id(), Ok(0x1234));
+
+    mock::respond(SbiRet::ok(1));
+    assert_eq!(sbi::base::probe_ext(sbi::timer::EXTENSION_ID), Ok(true));
+}
+
+#[test]
+fn unanswered_calls_are_not_supported() {
+    mock::reset();
+    assert_eq!(sbi::base::impl_id(), Err(Error::NotSupported));
+}
+
+#[test]
+fn records_calls() {
+    mock::reset();
+    mock::respond(SbiRet::ok(0));
+    mock::respond(SbiRet::ok(0));
+
+    sbi::hsm::start(3, 0x8020_0000, 0xdead).unwrap();
+    sbi::timer::set_timer(0x1_0000).unwrap();
+
+    assert_eq!(
+        mock::calls(),
+        [
+            Call {
+                ext: sbi::hsm::EXTENSION_ID,
+                fid: 0x00,
+                args: [3, 0x8020_0000, 0xdead, 0, 0, 0],
+            },
+            Call {
+                ext: sbi::timer::EXTENSION_ID,
+                fid: 0x00,
+                args: [0x1_0000, 0, 0, 0, 0, 0],
+            },
+        ]
+    );
+    assert!(mock::calls().is_empty());
+}
+
+#[test]
+fn filtered_responses_only_match_their_function() {
+    mock::reset();
+    mock::respond_to(sbi::base::EXTENSION_ID, 0x02, SbiRet::ok(7));
+
+    assert_eq!(sbi::base::impl_id(), Err(Error::NotSupported));
+    assert_eq!(sbi::base::impl_version(), Ok(7));
+}
+
+#[test]
+fn handler_answers_remaining_calls() {
+    mock::reset();
+    mock::set_handler(Some(|call| SbiRet::ok(call.args[0] + 1)));
+    mock::respond(SbiRet::err(Error::Denied));
+
+    assert_eq!(
+        sbi::base::probe_ext(sbi::timer::EXTENSION_ID),
+        Err(Error::Denied)
+    );
+    assert_eq!(sbi::base::mvendorid(), Ok(1));
+}
@@ -0,0 +1,77 @@
+//! Tests for probing the extensions of the mock firmware.
+
+use sbi::{
+    backend::SbiRet,
+    mock::{self, Call},
+    probe::{self, Extension},
+    Error,
+};
+
+/// A firmware that implements SBI v1.0 with the timer and HSM extensions,
+/// and that fails to probe the PMU extension.
+fn modern_firmware(call: &Call) -> SbiRet {
+    match (call.ext, call.fid) {
+        (sbi::base::EXTENSION_ID, 0x00) => SbiRet::ok(1 << 24),
+        (sbi::base::EXTENSION_ID, 0x03) => match call.args[0] as u32 {
+            sbi::timer::EXTENSION_ID | sbi::hsm::EXTENSION_ID => SbiRet::ok(1),
+            sbi::pmu::EXTENSION_ID => SbiRet::err(Error::Failed),
+            _ => SbiRet::ok(0),
+        },
+        _ => SbiRet::err(Error::NotSupported),
+    }
+}
+
+#[test]
+fn legacy_firmware_has_legacy_extensions() {
+    mock::reset();
+
+    let extensions = probe::probe();
+    assert_eq!(
+        extensions.iter().collect::<Vec<_>>(),
+        [Extension::LegacyConsole, Extension::LegacyTimer]
+    );
+
+    // a legacy firmware can't probe, so only the version is requested
+    let calls = mock::calls();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(
+        (calls[0].ext, calls[0].fid),
+        (sbi::base::EXTENSION_ID, 0x00)
+    );
+}
+
+#[test]
+fn modern_firmware_probes_every_extension() {
+    mock::reset();
+    mock::set_handler(Some(modern_firmware));
+
+    let extensions = probe::probe();
+    assert_eq!(
+        extensions.iter().collect::<Vec<_>>(),
+        [Extension::Timer, Extension::Hsm]
+    );
+    assert!(!extensions.contains(Extension::Pmu));
+
+    let probed = mock::calls()
+        .into_iter()
+        .filter(|call| (call.ext, call.fid) == (sbi::base::EXTENSION_ID, 0x03))
+        .map(|call| call.args[0] as u32)
+        .collect::<Vec<_>>();
+    let all = Extension::ALL
+        .iter()
+        .map(|ext| ext.id())
+        .collect::<Vec<_>>();
+    assert_eq!(probed, all);
+}
+
+#[test]
+fn extension_bits_round_trip() {
+    let mut extensions = probe::Extensions::empty();
+    extensions.insert(Extension::Dbcn);
+    extensions.insert(Extension::LegacyTimer);
+
+    let copy = probe::Extensions::from_bits(extensions.bits());
+    assert_eq!(copy, extensions);
+    assert!(copy.contains(Extension::Dbcn));
+    assert!(!copy.contains(Extension::Timer));
+}
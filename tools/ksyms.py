@@ -0,0 +1,116 @@
+#!/usr/bin/env python3
+"""
+Post-link tool that embeds a symbol table into the kernel binary.
+
+The kernel reserves space for the table inside the `.ksyms` section
+(see `crates/kernel/src/ksyms.rs` and `crates/kernel/lds/qemu.lds`).
+This script reads all function symbols from the `.symtab` of the linked
+kernel and writes them into the reserved section, so the kernel can
+resolve addresses to `function+offset` at runtime.
+
+It's run automatically by `tools/kernel-ld`, which is the linker of the
+kernel target, but it can also be run by hand on an existing kernel.
+
+Table layout (all integers are little endian):
+
+    magic:   b"KSYM"
+    count:   u32
+    entries: count * { addr: u64, size: u32, name_off: u32 }, sorted by `addr`
+    names:   nul-terminated, mangled symbol names
+
+Usage: ksyms.py <kernel-elf>
+"""
+
+import re
+import struct
+import sys
+
+MAGIC = b"KSYM"
+SECTION = ".ksyms"
+
+SHT_SYMTAB = 2
+STT_FUNC = 2
+
+# the hash inside legacy mangled names is useless for backtraces,
+# so it's stripped to keep the table small
+HASH = re.compile(rb"17h[0-9a-f]{16}E$")
+
+
+def read_sections(elf):
+    if elf[:4] != b"\x7fELF" or elf[4] != 2:
+        sys.exit("error: kernel is not a 64-bit ELF file")
+
+    shoff, = struct.unpack_from("<Q", elf, 0x28)
+    shentsize, shnum, shstrndx = struct.unpack_from("<HHH", elf, 0x3A)
+
+    sections = []
+    for idx in range(shnum):
+        off = shoff + idx * shentsize
+        name, type_, _, addr, offset, size, link = struct.unpack_from("<IIQQQQI", elf, off)
+        sections.append(dict(name=name, type=type_, addr=addr, offset=offset, size=size, link=link))
+
+    strtab = sections[shstrndx]
+    for section in sections:
+        start = strtab["offset"] + section["name"]
+        section["name"] = elf[start:elf.index(b"\0", start)].decode()
+
+    return sections
+
+
+def read_functions(elf, sections):
+    symtab = next(s for s in sections if s["type"] == SHT_SYMTAB)
+    strtab = sections[symtab["link"]]
+
+    functions = {}
+    for off in range(symtab["offset"], symtab["offset"] + symtab["size"], 24):
+        name, info, _, _, value, size = struct.unpack_from("<IBBHQQ", elf, off)
+        if info & 0xF != STT_FUNC or value == 0:
+            continue
+
+        start = strtab["offset"] + name
+        name = elf[start:elf.index(b"\0", start)]
+        functions.setdefault(value, (size, HASH.sub(b"E", name)))
+
+    return sorted(functions.items())
+
+
+def build_table(functions):
+    entries = bytearray()
+    names = bytearray()
+    header = len(MAGIC) + 4
+    names_start = header + len(functions) * 16
+
+    for addr, (size, name) in functions:
+        entries += struct.pack("<QII", addr, min(size, 0xFFFF_FFFF), names_start + len(names))
+        names += name + b"\0"
+
+    return MAGIC + struct.pack("<I", len(functions)) + entries + names
+
+
+def main():
+    if len(sys.argv) != 2:
+        sys.exit(__doc__.strip().splitlines()[-1])
+
+    path = sys.argv[1]
+    with open(path, "rb") as f:
+        elf = bytearray(f.read())
+
+    sections = read_sections(elf)
+    section = next((s for s in sections if s["name"] == SECTION), None)
+    if section is None:
+        sys.exit(f"error: kernel has no `{SECTION}` section")
+
+    table = build_table(read_functions(elf, sections))
+    if len(table) > section["size"]:
+        sys.exit(f"error: symbol table needs {len(table)} bytes, "
+                 f"but only {section['size']} bytes are reserved")
+
+    start = section["offset"]
+    elf[start:start + section["size"]] = table.ljust(section["size"], b"\0")
+
+    with open(path, "wb") as f:
+        f.write(elf)
+
+
+if __name__ == "__main__":
+    main()